@@ -1,10 +1,10 @@
 use webhook::client::{WebhookClient, WebhookResult};
 use webhook::models::NonLinkButtonStyle;
 
-const IMAGE_URL: &'static str = "https://cdn.discordapp.com/avatars/312157715449249795/a_b8b3b0c35f3dee2b6586a0dd58697e29.png";
+const IMAGE_URL: &str = "https://cdn.discordapp.com/avatars/312157715449249795/a_b8b3b0c35f3dee2b6586a0dd58697e29.png";
 
 #[tokio::main]
-async fn main() -> WebhookResult<()> {
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     dotenv::dotenv()?; // Load the .env file (excluded from the repository)
 
     let url = dotenv::var("URL")?;
@@ -30,8 +30,9 @@ async fn main() -> WebhookResult<()> {
 
 // to try out using application webhook run:
 // `application_webhook_example(&url).await?;`
+#[allow(dead_code)]
 async fn application_webhook_example(url: &str) -> WebhookResult<()> {
-    let client = WebhookClient::new(&url);
+    let client = WebhookClient::new(url);
     let webhook_info = client.get_information().await?;
     println!("webhook: {:?}", webhook_info);
 
@@ -0,0 +1,50 @@
+//! URLs of images hosted on the Discord CDN.
+
+const CDN_ROOT: &str = "https://cdn.discordapp.com";
+
+/// The number of default avatars.
+const DEFAULT_AVATAR_COUNT: u64 = 6;
+
+/// The avatar URL of the user `user_id` with the avatar `hash`.
+///
+/// Animated avatars (hash prefixed by `a_`) link the GIF, the other ones the PNG. A user without
+/// an avatar (empty `hash`) gets one of the default avatars, picked as Discord picks it.
+pub fn avatar_url(user_id: &str, hash: &str) -> String {
+    if hash.is_empty() {
+        let index = user_id.parse::<u64>().map_or(0, |id| (id >> 22) % DEFAULT_AVATAR_COUNT);
+        return format!("{}/embed/avatars/{}.png", CDN_ROOT, index);
+    }
+    let extension = if hash.starts_with("a_") { "gif" } else { "png" };
+    format!("{}/avatars/{}/{}.{}", CDN_ROOT, user_id, hash, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::avatar_url;
+
+    #[test]
+    fn static_avatar() {
+        assert_eq!(
+            avatar_url("80351110224678912", "8342729096ea3675442027381ff50dfe"),
+            "https://cdn.discordapp.com/avatars/80351110224678912/8342729096ea3675442027381ff50dfe.png"
+        );
+    }
+
+    #[test]
+    fn animated_avatar() {
+        assert_eq!(
+            avatar_url("80351110224678912", "a_8342729096ea3675442027381ff50dfe"),
+            "https://cdn.discordapp.com/avatars/80351110224678912/a_8342729096ea3675442027381ff50dfe.gif"
+        );
+    }
+
+    #[test]
+    fn default_avatar_without_hash() {
+        // (80351110224678912 >> 22) % 6 == 5
+        assert_eq!(
+            avatar_url("80351110224678912", ""),
+            "https://cdn.discordapp.com/embed/avatars/5.png"
+        );
+        assert_eq!(avatar_url("not a snowflake", ""), "https://cdn.discordapp.com/embed/avatars/0.png");
+    }
+}
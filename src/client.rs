@@ -1,28 +1,297 @@
-use hyper::body::Buf;
+use futures_util::stream::{self, Stream, StreamExt};
+use futures_util::future;
+use hyper::body::Bytes;
 use hyper::client::{Client, HttpConnector};
-use hyper::{Body, Method, Request, StatusCode, Uri};
+use hyper::header::HeaderValue;
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode, Uri};
+#[cfg(feature = "rustls")]
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+#[cfg(all(feature = "client", not(feature = "rustls")))]
 use hyper_tls::HttpsConnector;
 
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
+
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::time::Instant;
 
-use crate::models::{DiscordApiCompatible, Message, MessageContext, Webhook};
+use crate::models::{
+    AllowedMentions, Attachment, AttachmentData, DiscordApiCompatible, EditMessage, LimitProfile, Limits, Message,
+    MessageContext, SentMessage, ValidationError, Webhook, WebhookInfo,
+};
+use crate::error::WebhookError;
+use crate::rate_limit::{is_global_rate_limit, parse_bucket_reset_after, parse_retry_after, RetryPolicy};
 
-pub type WebhookResult<Type> = std::result::Result<Type, Box<dyn std::error::Error + Send + Sync>>;
+pub type WebhookResult<Type> = std::result::Result<Type, WebhookError>;
 
 /// A Client that sends webhooks for discord.
+///
+/// Cloning the client is cheap, the clones share the underlying connection pool.
+#[derive(Clone)]
 pub struct WebhookClient {
+    inner: Arc<ClientInner>,
+}
+
+impl std::fmt::Debug for WebhookClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookClient")
+            .field("url", &redact_url(&self.inner.url))
+            .field("timeout", &self.inner.timeout)
+            .field("limit_profile", &self.inner.limit_profile)
+            .field("limits", &self.inner.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The state shared by clones of a `WebhookClient`.
+#[derive(Clone)]
+struct ClientInner {
     client: Client<HttpsConnector<HttpConnector>>,
+    /// The settings `client` is built with.
+    connection_settings: ConnectionSettings,
     url: String,
+    timeout: Option<Duration>,
+    limit_profile: LimitProfile,
+    limits: Limits,
+    check_url: bool,
+    /// Allowed mentions of the sent messages which do not set their own.
+    default_allowed_mentions: Option<AllowedMentions>,
+    retry_policy: RetryPolicy,
+    /// Limits the number of requests in flight, unlimited if None.
+    concurrency_limit: Option<Arc<Semaphore>>,
+    /// The number of redirects followed, redirects are not followed if 0.
+    max_redirects: u32,
+    /// The webhook information fetched by `connect`.
+    information: Option<Webhook>,
+    sanitize_control_chars: bool,
+    /// The content type of JSON request bodies.
+    json_content_type: String,
+    /// The number of retries of rate limited (429) sends, see `with_max_retries`.
+    max_rate_limit_retries: usize,
+    /// The end of the global rate limit, no request is sent before. Shared by all clones.
+    global_rate_limit_until: Arc<Mutex<Option<Instant>>>,
+    /// The reset of the exhausted rate limit bucket of the webhook, as reported by the last
+    /// response. Shared by all clones.
+    bucket_reset_at: Arc<Mutex<Option<Instant>>>,
+}
+
+/// Connection pool and connector settings of the HTTP client, hyper's defaults if None.
+#[derive(Clone, Copy, Default)]
+struct ConnectionSettings {
+    max_idle_per_host: Option<usize>,
+    idle_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+}
+
+/// Creates the HTTP client with the connection `settings`.
+fn build_client(settings: &ConnectionSettings) -> Client<HttpsConnector<HttpConnector>> {
+    let mut http_connector = HttpConnector::new();
+    http_connector.enforce_http(false);
+    http_connector.set_connect_timeout(settings.connect_timeout);
+
+    let mut builder = Client::builder();
+    if let Some(max_idle_per_host) = settings.max_idle_per_host {
+        builder.pool_max_idle_per_host(max_idle_per_host);
+    }
+    if let Some(idle_timeout) = settings.idle_timeout {
+        builder.pool_idle_timeout(idle_timeout);
+    }
+    builder.build::<_, hyper::Body>(https_connector(http_connector))
+}
+
+/// Creates the TLS connector of the enabled TLS backend (native TLS).
+#[cfg(all(feature = "client", not(feature = "rustls")))]
+fn https_connector(http_connector: HttpConnector) -> HttpsConnector<HttpConnector> {
+    HttpsConnector::new_with_connector(http_connector)
+}
+
+/// Creates the TLS connector of the enabled TLS backend (rustls with the webpki root certificates).
+#[cfg(feature = "rustls")]
+fn https_connector(http_connector: HttpConnector) -> HttpsConnector<HttpConnector> {
+    HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .wrap_connector(http_connector)
 }
 
 impl WebhookClient {
     pub fn new(url: &str) -> Self {
-        let https_connector = HttpsConnector::new();
-        let client = Client::builder().build::<_, hyper::Body>(https_connector);
+        #[cfg(feature = "tracing")]
+        if deprecated_host_normalized(url).is_some() {
+            tracing::warn!(
+                url = %redact_url(url),
+                "The webhook URL uses the deprecated discordapp.com host, see `with_host_normalization`"
+            );
+        }
+        let connection_settings = ConnectionSettings::default();
         Self {
-            client,
-            url: url.to_owned(),
+            inner: Arc::new(ClientInner {
+                client: build_client(&connection_settings),
+                connection_settings,
+                url: url.to_owned(),
+                timeout: None,
+                limit_profile: LimitProfile::default(),
+                limits: Limits::default(),
+                check_url: true,
+                default_allowed_mentions: None,
+                retry_policy: RetryPolicy::default(),
+                concurrency_limit: None,
+                max_redirects: 0,
+                information: None,
+                sanitize_control_chars: false,
+                json_content_type: "application/json".to_string(),
+                max_rate_limit_retries: 0,
+                global_rate_limit_until: Arc::new(Mutex::new(None)),
+                bucket_reset_at: Arc::new(Mutex::new(None)),
+            }),
+        }
+    }
+
+    /// Creates a client and verifies that the webhook exists by fetching its information,
+    /// so that an invalid or deleted webhook is reported right away instead of on the first send.
+    ///
+    /// The fetched information is available via `information`.
+    pub async fn connect(url: &str) -> WebhookResult<WebhookClient> {
+        let mut client = WebhookClient::new(url);
+        let webhook = client.get_information().await?;
+        Arc::make_mut(&mut client.inner).information = Some(webhook);
+        Ok(client)
+    }
+
+    /// The webhook information fetched when the client was created by `connect`.
+    pub fn information(&self) -> Option<&Webhook> {
+        self.inner.information.as_ref()
+    }
+
+    /// Sets the default timeout of requests (including reading the response).
+    /// Requests do not time out by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.inner).timeout = Some(timeout);
+        self
+    }
+
+    /// Configures the connection pool: at most `max_idle_per_host` idle connections are kept
+    /// per host, each for at most `idle_timeout`. By default, the pool is unbounded and idle
+    /// connections are closed after 90 seconds.
+    pub fn with_pool_settings(mut self, max_idle_per_host: usize, idle_timeout: Duration) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.connection_settings.max_idle_per_host = Some(max_idle_per_host);
+        inner.connection_settings.idle_timeout = Some(idle_timeout);
+        inner.client = build_client(&inner.connection_settings);
+        self
+    }
+
+    /// Sets the timeout of establishing a connection, so that an unreachable host fails fast
+    /// even without (or with a long) request timeout, see `with_timeout`.
+    /// Connecting does not time out by default.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.connection_settings.connect_timeout = Some(connect_timeout);
+        inner.client = build_client(&inner.connection_settings);
+        self
+    }
+
+    /// Sets the allowed mentions of sent messages which do not set their own, e.g.
+    /// `AllowedMentions::none()` to never ping anybody by accident.
+    pub fn with_default_allowed_mentions(mut self, allowed_mentions: AllowedMentions) -> Self {
+        Arc::make_mut(&mut self.inner).default_allowed_mentions = Some(allowed_mentions);
+        self
+    }
+
+    /// Sets whether redirect responses are followed, at most `max` times per request.
+    /// Redirects are not followed by default, the redirect response is handled as any other.
+    ///
+    /// A `307`/`308` redirect repeats the request, a `301`/`302`/`303` redirect is followed
+    /// only by `GET` requests (e.g. `get_information`). Following the redirect of a request with
+    /// a body (e.g. a send) with a `GET` would drop the message, such a redirect is an error.
+    /// Redirects to a different host are not followed either, the URL carries the webhook token.
+    pub fn with_redirect_policy(mut self, follow: bool, max: u32) -> Self {
+        Arc::make_mut(&mut self.inner).max_redirects = if follow { max } else { 0 };
+        self
+    }
+
+    /// Sets whether control characters (e.g. `\0`) are removed from the content and the embed
+    /// texts of sent messages, newlines and tabs are kept. Messages are sent as they are
+    /// by default.
+    pub fn with_sanitize_control_chars(mut self, sanitize: bool) -> Self {
+        Arc::make_mut(&mut self.inner).sanitize_control_chars = sanitize;
+        self
+    }
+
+    /// Sets the content type of requests with a JSON body, `application/json` by default.
+    /// Useful behind proxies expecting e.g. `application/json; charset=utf-8`.
+    pub fn with_json_content_type(mut self, content_type: &str) -> Self {
+        Arc::make_mut(&mut self.inner).json_content_type = content_type.to_string();
+        self
+    }
+
+    /// Whether the webhook URL uses the deprecated `discordapp.com` host instead of `discord.com`.
+    pub fn uses_deprecated_host(&self) -> bool {
+        deprecated_host_normalized(&self.inner.url).is_some()
+    }
+
+    /// Sets whether a webhook URL using the deprecated `discordapp.com` host is rewritten to
+    /// use `discord.com`. URLs are used as they are by default.
+    pub fn with_host_normalization(mut self, normalize: bool) -> Self {
+        if let Some(url) = deprecated_host_normalized(&self.inner.url).filter(|_| normalize) {
+            Arc::make_mut(&mut self.inner).url = url;
         }
+        self
+    }
+
+    /// Retries rate limited (429) sends at most `max_retries` times, after the time Discord
+    /// asks for (the `retry_after` field of the body, or the `Retry-After` header). Rate limited
+    /// sends are not retried by default (0). Applies to `send` and the other sends returning
+    /// `bool`, see `with_retry_policy` for `send_reliable`.
+    ///
+    /// Streamed attachments cannot be retried, they are read by the first attempt.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        Arc::make_mut(&mut self.inner).max_rate_limit_retries = max_retries;
+        self
+    }
+
+    /// Sets how `send_reliable` retries failed sends.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        Arc::make_mut(&mut self.inner).retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the upload limits used to validate attachments, see `LimitProfile`.
+    pub fn with_limit_profile(mut self, limit_profile: LimitProfile) -> Self {
+        Arc::make_mut(&mut self.inner).limit_profile = limit_profile;
+        self
+    }
+
+    /// Overrides the limits enforced by the validation, for targets which are not Discord
+    /// (e.g. a self-hosted implementation of the webhook API), see `Limits`.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        Arc::make_mut(&mut self.inner).limits = limits;
+        self
+    }
+
+    /// Allows at most `max_concurrency` requests of the client to be in flight at once, the other
+    /// requests wait for their turn. Waiting counts towards the timeout. Zero means no limit.
+    ///
+    /// The limit is shared by the clones made after this call, clones made before keep their
+    /// own limit.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        Arc::make_mut(&mut self.inner).concurrency_limit =
+            Some(max_concurrency).filter(|max| *max > 0).map(|max| Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// Disables the check that the URL looks like a webhook execute URL
+    /// (`.../api/webhooks/{id}/{token}`), e.g. for custom endpoints or proxies.
+    pub fn without_url_check(mut self) -> Self {
+        Arc::make_mut(&mut self.inner).check_url = false;
+        self
     }
 
     /// Example
@@ -36,396 +305,3543 @@ impl WebhookClient {
     where
         Func: Fn(&mut Message) -> &mut Message,
     {
-        let mut message = Message::new();
-        function(&mut message);
-        let mut message_context = MessageContext::new();
-        match message.check_compatibility(&mut message_context) {
-            Ok(_) => (),
-            Err(error_message) => {
-                return Err(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    error_message,
-                )));
-            }
-        };
-        let result = self.send_message(&message).await?;
+        let message = self.build_message(function)?;
+        let result = self.execute(&message, self.inner.timeout).await?;
 
         Ok(result)
     }
 
-    pub async fn send_message(&self, message: &Message) -> WebhookResult<bool> {
-        let body = serde_json::to_string(message)?;
-        let request = Request::builder()
-            .method(Method::POST)
-            .uri(&self.url)
-            .header("content-type", "application/json")
-            .body(Body::from(body))?;
-        let response = self.client.request(request).await?;
-
-        // https://discord.com/developers/docs/resources/webhook#execute-webhook
-        // execute webhook returns either NO_CONTENT or a message
-        if response.status() == StatusCode::NO_CONTENT {
-            Ok(true)
-        } else {
-            let body_bytes = hyper::body::to_bytes(response.into_body()).await?;
-            let err_msg = match String::from_utf8(body_bytes.to_vec()) {
-                Ok(msg) => msg,
-                Err(err) => {
-                    "Error reading Discord API error message:".to_string() + &err.to_string()
-                }
-            };
-
-            Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                err_msg,
-            )))
-        }
+    /// Same as `send`, but `timeout` takes precedence over the timeout of the client.
+    pub async fn send_with_timeout<Func>(
+        &self,
+        function: Func,
+        timeout: Duration,
+    ) -> WebhookResult<bool>
+    where
+        Func: Fn(&mut Message) -> &mut Message,
+    {
+        let message = self.build_message(function)?;
+        self.execute(&message, Some(timeout)).await
     }
 
-    pub async fn get_information(&self) -> WebhookResult<Webhook> {
-        let response = self.client.get(Uri::from_str(&self.url)?).await?;
-        let body = hyper::body::aggregate(response).await?;
-        let webhook = serde_json::from_reader(body.reader())?;
+    /// Same as `send`, but the message is posted to the thread `thread_id` of the webhook's
+    /// channel. `thread_id` takes precedence over the thread set by `Message::thread_id`.
+    pub async fn send_to_thread<Func>(&self, thread_id: &str, function: Func) -> WebhookResult<bool>
+    where
+        Func: Fn(&mut Message) -> &mut Message,
+    {
+        let mut message = self.build_message(function)?;
+        message.thread_id(thread_id);
+        self.execute(&message, self.inner.timeout).await
+    }
 
-        Ok(webhook)
+    /// Same as `send`, with additional query parameters (e.g. parameters not yet supported by
+    /// the client). The parameters are percent-encoded and appended to the query of the URL.
+    pub async fn send_with_params<Func>(
+        &self,
+        function: Func,
+        params: &[(&str, &str)],
+    ) -> WebhookResult<bool>
+    where
+        Func: Fn(&mut Message) -> &mut Message,
+    {
+        let message = self.build_message(function)?;
+        self.execute_with_params(&message, self.inner.timeout, params, None).await
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::models::{ActionRow, DiscordApiCompatible, Embed, EmbedAuthor, EmbedField, EmbedFooter, Message, MessageContext, NonLinkButtonStyle};
+    /// Same as `send`, the request carries `request_id` in the `X-Request-Id` header so that the
+    /// send can be correlated across logs. The id is also recorded in the `tracing` span and
+    /// added to the error message of a failed send.
+    pub async fn send_with_request_id<Func>(&self, function: Func, request_id: &str) -> WebhookResult<bool>
+    where
+        Func: Fn(&mut Message) -> &mut Message,
+    {
+        let message = self.build_message(function)?;
+        self.execute_with_params(&message, self.inner.timeout, &[], Some(request_id))
+            .await
+            .map_err(|err| WebhookError::Request {
+                request_id: request_id.to_string(),
+                error: Box::new(err),
+            })
+    }
 
-    fn assert_message_error<BuildFunc, MessagePred>(
-        message_build: BuildFunc,
-        msg_pred: MessagePred,
-    )
+    /// Validates the message and sends it on a background task, for messages whose delivery is
+    /// not critical (e.g. telemetry). A failed send is ignored (and logged with the `tracing`
+    /// feature); the send waits for a global rate limit like any other. The task is spawned on
+    /// the current tokio runtime.
+    ///
+    /// # Return value
+    /// The handle of the background task. Error variant contains the validation error or
+    /// the error of calling it outside of a tokio runtime, nothing is sent then
+    pub fn send_best_effort<Func>(&self, function: Func) -> WebhookResult<JoinHandle<()>>
     where
-        BuildFunc: Fn(&mut Message) -> &mut Message,
-        MessagePred: Fn(&str) -> bool,
+        Func: Fn(&mut Message) -> &mut Message,
     {
-        let mut message = Message::new();
-        message_build(&mut message);
-        match message.check_compatibility(&mut MessageContext::new()) {
-            Err(err) => {
-                assert!(
-                    msg_pred(&err.to_string()),
-                    "Unexpected error message {}",
-                    err.to_string()
-                )
+        let message = self.build_message(function)?;
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|err| WebhookError::other(format!("Cannot send in the background: {}", err)))?;
+        let client = self.clone();
+        Ok(runtime.spawn(async move {
+            if let Err(_err) = client.execute(&message, client.inner.timeout).await {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_err, "Best effort send failed");
             }
-            Ok(_) => assert!(false, "Error is expected"),
-        };
+        }))
     }
 
-    fn contains_all_predicate(needles: Vec<&str>) -> Box<dyn Fn(&str) -> bool> {
-        let owned_needles: Vec<String> = needles.iter().map(|n| n.to_string()).collect();
-        Box::new(move |haystack| {
-            let lower_haystack = haystack.to_lowercase();
-            owned_needles
-                .iter()
-                .all(|needle| lower_haystack.contains(needle))
-        })
+    /// Validates and sends an already built message. The message is serialized only if it is valid.
+    pub async fn send_message(&self, message: &Message) -> WebhookResult<bool> {
+        self.validate(message)?;
+        self.execute(message, self.inner.timeout).await
     }
 
-    fn assert_valid_message<BuildFunc>(func: BuildFunc)
+    /// Validates and sends an already built message, waiting for Discord to confirm the
+    /// message was created (the `wait` query parameter).
+    ///
+    /// # Return value
+    /// The created message, e.g. to edit or delete it later
+    pub async fn send_message_wait(&self, message: &Message) -> WebhookResult<SentMessage> {
+        self.validate(message)?;
+        self.execute_wait(message).await
+    }
+
+    /// Sends the message without validating it at all.
+    ///
+    /// Intended for hot paths where the message has already been validated. An invalid message
+    /// is rejected by Discord after being sent, with a less descriptive error.
+    pub async fn send_unchecked(&self, message: &Message) -> WebhookResult<bool> {
+        self.execute(message, self.inner.timeout).await
+    }
+
+    /// Sends the message, retrying rate limited (429) and, depending on the `RetryPolicy`
+    /// of the client, failed (5xx) attempts. The message is validated once and all attempts carry
+    /// the same nonce.
+    ///
+    /// Streamed attachments cannot be retried, they are read by the first attempt.
+    ///
+    /// # Return value
+    /// The created message
+    pub async fn send_reliable<Func>(&self, function: Func) -> WebhookResult<SentMessage>
     where
-        BuildFunc: Fn(&mut Message) -> &mut Message,
+        Func: Fn(&mut Message) -> &mut Message,
     {
-        let mut message = Message::new();
-        func(&mut message);
-        if let Err(unexpected) = message.check_compatibility(&mut MessageContext::new()) {
-            assert!(false, "Unexpected validation error {}", unexpected);
+        let mut message = self.build_message(function)?;
+        if message.nonce.is_none() {
+            message.nonce = Some(nonce());
+        }
+
+        let policy = self.inner.retry_policy;
+        let mut retry = 0;
+        loop {
+            let response = self
+                .execute_raw(&message, self.inner.timeout, &[("wait", "true")], None)
+                .await?;
+            if response.status.is_success() {
+                return Ok(serde_json::from_slice(&response.body)?);
+            }
+
+            let delay = if response.status == StatusCode::TOO_MANY_REQUESTS {
+                Some(parse_retry_after(&response.headers, &response.body).unwrap_or(policy.base_delay))
+            } else if response.status.is_server_error() && policy.retry_server_errors {
+                Some(policy.server_error_delay(retry))
+            } else {
+                None
+            };
+            match delay {
+                Some(delay) if retry < policy.max_retries => {
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                _ => return Err(api_error(response.status, &response.body)),
+            }
         }
     }
 
-    #[test]
-    fn empty_action_row_prohibited() {
-        assert_message_error(
-            |message| message.action_row(|row| row),
-            contains_all_predicate(vec!["action row", "empty"]),
-        );
+    /// Builds the edit of the message `message_id` sent by the webhook, see `edit_message`.
+    ///
+    /// # Return value
+    /// The edited message
+    pub async fn edit<Func>(&self, message_id: &str, function: Func) -> WebhookResult<SentMessage>
+    where
+        Func: Fn(&mut EditMessage) -> &mut EditMessage,
+    {
+        let mut edit = EditMessage::new();
+        function(&mut edit);
+        self.edit_message(message_id, &edit).await
     }
 
-    #[test]
-    fn send_message_custom_id_reuse_prohibited() {
-        assert_message_error(
-            |message| {
-                message.action_row(|row| {
-                    row.regular_button(|button| {
-                        button.custom_id("0").style(NonLinkButtonStyle::Primary)
-                    })
-                    .regular_button(|button| {
-                        button.custom_id("0").style(NonLinkButtonStyle::Primary)
-                    })
+    /// Validates the edit and applies it to the message `message_id` sent by the webhook.
+    ///
+    /// # Return value
+    /// The edited message
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "webhook.edit_message", skip_all, fields(url = %redact_url(&self.inner.url)))
+    )]
+    pub async fn edit_message(&self, message_id: &str, edit: &EditMessage) -> WebhookResult<SentMessage> {
+        self.validate(edit)?;
+        if self.inner.check_url {
+            check_webhook_url(&self.inner.url)?;
+        }
+        with_timeout(self.inner.timeout, async {
+            let _permit = self.acquire_permit().await?;
+            let payload = serde_json::to_string(edit)?;
+            let response = self
+                .send_request(|| {
+                    let request = Request::builder()
+                        .method(Method::PATCH)
+                        .uri(message_url(&self.inner.url, message_id, edit.thread_id.as_deref()));
+                    if edit.attachments.is_empty() {
+                        Ok(request
+                            .header("content-type", self.inner.json_content_type.as_str())
+                            .body(Body::from(payload.clone()))?)
+                    } else {
+                        let boundary = multipart_boundary();
+                        Ok(request
+                            .header(
+                                "content-type",
+                                format!("multipart/form-data; boundary={}", boundary),
+                            )
+                            .body(multipart_body(&boundary, &payload, &edit.attachments)?)?)
+                    }
                 })
-            },
-            contains_all_predicate(vec!["twice"]),
-        );
-    }
+                .await?;
+            #[cfg(feature = "tracing")]
+            tracing::info!(status = response.status.as_u16(), "Webhook message edited");
 
-    #[test]
-    fn send_message_custom_id_reuse_prohibited_across_action_rows() {
-        assert_message_error(
-            |message| {
-                message
-                    .action_row(|row| {
-                        row.regular_button(|button| {
-                            button.custom_id("0").style(NonLinkButtonStyle::Primary)
-                        })
-                    })
-                    .action_row(|row| {
-                        row.regular_button(|button| {
-                            button.custom_id("0").style(NonLinkButtonStyle::Primary)
-                        })
-                    })
-            },
-            contains_all_predicate(vec!["twice"]),
-        );
+            let RawResponse { status, body, .. } = response;
+            if status.is_success() {
+                Ok(serde_json::from_slice(&body)?)
+            } else if status == StatusCode::NOT_FOUND {
+                Err(unknown_message_error(message_id))
+            } else {
+                Err(api_error(status, &body))
+            }
+        })
+        .await
     }
 
-    #[test] fn send_message_button_style_required() {
-        assert_message_error(
-            |message| message.action_row(|row| row.regular_button(|button| button.custom_id("0"))),
-            contains_all_predicate(vec!["style"]),
-        );
-    }
+    /// Deletes the message `message_id` sent by the webhook, `thread_id` is the thread
+    /// of the message if it was posted to a thread.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "webhook.delete_message", skip_all, fields(url = %redact_url(&self.inner.url)))
+    )]
+    pub async fn delete_message(&self, message_id: &str, thread_id: Option<&str>) -> WebhookResult<()> {
+        if self.inner.check_url {
+            check_webhook_url(&self.inner.url)?;
+        }
+        with_timeout(self.inner.timeout, async {
+            let _permit = self.acquire_permit().await?;
+            let response = self
+                .send_request(|| {
+                    Ok(Request::builder()
+                        .method(Method::DELETE)
+                        .uri(message_url(&self.inner.url, message_id, thread_id))
+                        .body(Body::empty())?)
+                })
+                .await?;
+            #[cfg(feature = "tracing")]
+            tracing::info!(status = response.status.as_u16(), "Webhook message deleted");
 
-    #[test]
-    fn send_message_url_required() {
-        assert_message_error(
-            |message| message.action_row(|row| row.link_button(|button| button.label("test"))),
-            contains_all_predicate(vec!["url"]),
-        );
+            let RawResponse { status, body, .. } = response;
+            if status.is_success() {
+                Ok(())
+            } else if status == StatusCode::NOT_FOUND {
+                Err(unknown_message_error(message_id))
+            } else {
+                Err(api_error(status, &body))
+            }
+        })
+        .await
     }
 
-    #[test]
-    fn send_message_max_action_rows_enforced() {
-        assert_message_error(
-            |message| {
-                for _ in 0..(Message::ACTION_ROW_COUNT_INTERVAL.max_allowed + 1) {
-                    message.action_row(|row| row);
+    /// Sends the messages one after another. If a send fails, the messages sent so far are
+    /// deleted again (best effort) and the error of the failed send is returned.
+    ///
+    /// All messages are validated before the first one is sent. The sequence is not truly
+    /// atomic though: the sent messages are visible until they are deleted, and a failed
+    /// deletion leaves the message in place.
+    ///
+    /// # Return value
+    /// The sent messages, in the order of `messages`
+    pub async fn send_sequence(&self, messages: &[Message]) -> WebhookResult<Vec<SentMessage>> {
+        for message in messages.iter() {
+            self.validate(message)?;
+        }
+
+        let mut sent: Vec<SentMessage> = vec![];
+        for message in messages.iter() {
+            match self.execute_wait(message).await {
+                Ok(sent_message) => sent.push(sent_message),
+                Err(err) => {
+                    for (sent_message, message) in sent.iter().zip(messages.iter()).rev() {
+                        let _ = self.delete_message(&sent_message.id, message.thread_id.as_deref()).await;
+                    }
+                    return Err(err);
                 }
-                message
-            },
-            contains_all_predicate(vec!["interval", "row"]),
-        );
+            }
+        }
+        Ok(sent)
     }
 
-    #[test]
-    fn send_message_max_label_len_enforced() {
-        assert_message_error(
-            |message| {
-                message.action_row(|row| {
-                    row.regular_button(|btn| {
-                        btn.style(NonLinkButtonStyle::Primary)
-                            .custom_id("a")
-                            .label(&"l".repeat(Message::LABEL_LEN_INTERVAL.max_allowed + 1))
-                    })
-                })
-            },
-            contains_all_predicate(vec!["interval", "label"]),
-        );
+    /// Applies the edits (message id, edit) concurrently, see `edit_message`.
+    ///
+    /// # Return value
+    /// The results of the edits, in the order of `edits`
+    pub async fn edit_many(&self, edits: Vec<(String, EditMessage)>) -> Vec<WebhookResult<SentMessage>> {
+        let edits = edits
+            .iter()
+            .map(|(message_id, edit)| self.edit_message(message_id, edit));
+        future::join_all(edits).await
     }
 
-    #[test]
-    fn send_message_custom_id_required() {
-        assert_message_error(
-            |message| {
-                message.action_row(|row| {
-                    row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary))
-                })
-            },
-            contains_all_predicate(vec!["custom id"]),
-        );
-    }
+    /// Sends the same message through all `clients` concurrently.
+    ///
+    /// The message is built once and validated against the limits of every client (see
+    /// `with_limit_profile` and `with_limits`) before any of them sends it. A message invalid for
+    /// some client is reported as a single error and nothing is sent. No clients mean no
+    /// requests and an empty result.
+    ///
+    /// # Return value
+    /// The results of the sends, in the order of `clients`
+    pub async fn send_to_all<Func>(
+        clients: &[WebhookClient],
+        function: Func,
+    ) -> WebhookResult<Vec<WebhookResult<bool>>>
+    where
+        Func: Fn(&mut Message) -> &mut Message,
+    {
+        if clients.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut message = Message::new();
+        function(&mut message);
+        for client in clients.iter() {
+            client.validate(&message)?;
+        }
 
-    #[test]
-    fn send_message_max_custom_id_len_enforced() {
-        assert_message_error(
-            |message| {
-                message.action_row(|row| {
-                    row.regular_button(|btn| {
-                        btn.style(NonLinkButtonStyle::Primary).custom_id(
-                            &"a".repeat(Message::CUSTOM_ID_LEN_INTERVAL.max_allowed + 1),
-                        )
-                    })
-                })
-            },
-            contains_all_predicate(vec!["interval", "custom id"]),
-        );
+        let sends = clients
+            .iter()
+            .map(|client| client.execute(&message, client.inner.timeout));
+        Ok(future::join_all(sends).await)
     }
 
-    #[test]
-    fn max_button_count_enforced() {
+    /// Builds a message using the builder `function` and validates it.
+    fn build_message<Func>(&self, function: Func) -> WebhookResult<Message>
+    where
+        Func: Fn(&mut Message) -> &mut Message,
+    {
+        let mut message = Message::new();
+        function(&mut message);
+        self.validate(&message)?;
+        Ok(message)
+    }
+
+    /// Checks the message against the Discord API constraints (and the client's limits).
+    fn validate<T: DiscordApiCompatible>(&self, message: &T) -> WebhookResult<()> {
+        let mut message_context =
+            MessageContext::with_limit_profile(self.inner.limit_profile).with_limits(self.inner.limits);
+        message.check_compatibility(&mut message_context)?;
+        Ok(())
+    }
+
+    /// Waits until a request may be sent with respect to `with_max_concurrency`. The request is
+    /// in flight until the permit is dropped.
+    async fn acquire_permit(&self) -> WebhookResult<Option<SemaphorePermit<'_>>> {
+        match &self.inner.concurrency_limit {
+            Some(semaphore) => Ok(Some(semaphore.acquire().await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Builds the HTTP request which sending the message would make, without sending it
+    /// (and without validating the message), e.g. to reproduce an issue with curl.
+    ///
+    /// The request contains the webhook token, use `info_url_redacted` when logging the URL.
+    /// Streamed attachments are read into the body and cannot be sent afterwards.
+    pub async fn build_request(&self, message: &Message) -> WebhookResult<Request<Bytes>> {
+        let (parts, body) = self.execute_request(message, &[])?.into_parts();
+        let body = hyper::body::to_bytes(body).await?;
+        Ok(Request::from_parts(parts, body))
+    }
+
+    /// Creates the execute webhook request, a JSON one or a multipart one with attachments.
+    fn execute_request(&self, message: &Message, params: &[(&str, &str)]) -> WebhookResult<Request<Body>> {
+        let default_allowed_mentions = match (&message.allow_mentions, &self.inner.default_allowed_mentions) {
+            (None, Some(default_allowed_mentions)) => Some(default_allowed_mentions),
+            _ => None,
+        };
+        let payload = if default_allowed_mentions.is_none() && !self.inner.sanitize_control_chars {
+            serde_json::to_string(message)?
+        } else {
+            let mut payload = serde_json::to_value(message)?;
+            if let Some(default_allowed_mentions) = default_allowed_mentions {
+                payload["allowed_mentions"] = serde_json::to_value(default_allowed_mentions)?;
+            }
+            if self.inner.sanitize_control_chars {
+                for field in ["content", "embeds"].iter() {
+                    if let Some(value) = payload.get_mut(*field) {
+                        strip_control_chars(value);
+                    }
+                }
+            }
+            payload.to_string()
+        };
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(execute_url(&self.inner.url, message.thread_id.as_deref(), params));
+        let request = if message.attachments.is_empty() {
+            request
+                .header("content-type", self.inner.json_content_type.as_str())
+                .body(Body::from(payload))?
+        } else {
+            let boundary = multipart_boundary();
+            request
+                .header(
+                    "content-type",
+                    format!("multipart/form-data; boundary={}", boundary),
+                )
+                .body(multipart_body(&boundary, &payload, &message.attachments)?)?
+        };
+        Ok(request)
+    }
+
+    /// Sends the message with `wait=true`, Discord then responds with the created message.
+    async fn execute_wait(&self, message: &Message) -> WebhookResult<SentMessage> {
+        let response = self
+            .execute_raw(message, self.inner.timeout, &[("wait", "true")], None)
+            .await?;
+        if response.status.is_success() {
+            Ok(serde_json::from_slice(&response.body)?)
+        } else {
+            Err(api_error(response.status, &response.body))
+        }
+    }
+
+    async fn execute(&self, message: &Message, timeout: Option<Duration>) -> WebhookResult<bool> {
+        self.execute_with_params(message, timeout, &[], None).await
+    }
+
+    async fn execute_with_params(
+        &self,
+        message: &Message,
+        timeout: Option<Duration>,
+        params: &[(&str, &str)],
+        request_id: Option<&str>,
+    ) -> WebhookResult<bool> {
+        let mut response = self.execute_raw(message, timeout, params, request_id).await?;
+        let mut retry = 0;
+        while response.status == StatusCode::TOO_MANY_REQUESTS && retry < self.inner.max_rate_limit_retries {
+            let delay = parse_retry_after(&response.headers, &response.body)
+                .unwrap_or(self.inner.retry_policy.base_delay);
+            #[cfg(feature = "tracing")]
+            tracing::info!(delay_ms = delay.as_millis() as u64, "Rate limited, retrying");
+            tokio::time::sleep(delay).await;
+            retry += 1;
+            response = self.execute_raw(message, timeout, params, request_id).await?;
+        }
+
+        // https://discord.com/developers/docs/resources/webhook#execute-webhook
+        // execute webhook returns either NO_CONTENT or a message
+        if response.status == StatusCode::NO_CONTENT {
+            Ok(true)
+        } else {
+            Err(api_error(response.status, &response.body))
+        }
+    }
+
+    /// Sends the message with the additional query `params` (e.g. `wait`) and the `X-Request-Id`
+    /// header if there is a `request_id`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "webhook.execute",
+            skip_all,
+            fields(url = %redact_url(&self.inner.url), request_id = request_id)
+        )
+    )]
+    async fn execute_raw(
+        &self,
+        message: &Message,
+        timeout: Option<Duration>,
+        params: &[(&str, &str)],
+        request_id: Option<&str>,
+    ) -> WebhookResult<RawResponse> {
+        if self.inner.check_url {
+            check_webhook_url(&self.inner.url)?;
+        }
+        with_timeout(timeout, async {
+            let _permit = self.acquire_permit().await?;
+            let response = self
+                .send_request(|| {
+                    let mut request = self.execute_request(message, params)?;
+                    if let Some(request_id) = request_id {
+                        request
+                            .headers_mut()
+                            .insert("x-request-id", HeaderValue::from_str(request_id)?);
+                    }
+                    Ok(request)
+                })
+                .await?;
+            #[cfg(feature = "tracing")]
+            tracing::info!(status = response.status.as_u16(), "Webhook executed");
+            Ok(response)
+        })
+        .await
+    }
+
+    /// Sends the request created by `make_request` (see `request_following_redirects`) once
+    /// the global rate limit is over and reads the response. The rate limits reported by
+    /// the response are recorded, so that all requests of the client (and its clones) respect
+    /// them.
+    async fn send_request<F>(&self, make_request: F) -> WebhookResult<RawResponse>
+    where
+        F: Fn() -> WebhookResult<Request<Body>>,
+    {
+        self.wait_for_global_rate_limit().await;
+        let response = self.request_following_redirects(make_request).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+        if status == StatusCode::TOO_MANY_REQUESTS && is_global_rate_limit(&headers, &body) {
+            let retry_after = parse_retry_after(&headers, &body).unwrap_or(self.inner.retry_policy.base_delay);
+            let mut until = lock_rate_limit(&self.inner.global_rate_limit_until);
+            *until = until.max(Some(Instant::now() + retry_after));
+        }
+        *lock_rate_limit(&self.inner.bucket_reset_at) =
+            parse_bucket_reset_after(&headers).map(|reset_after| Instant::now() + reset_after);
+        Ok(RawResponse { status, headers, body })
+    }
+
+    /// When the next send can proceed without being rate limited, according to the rate limit
+    /// headers of the previous responses (the webhook's bucket and the global rate limit).
+    /// Useful to pace sends by an external scheduler.
+    ///
+    /// # Return value
+    /// None if a send can proceed right away
+    pub fn next_available(&self) -> Option<Instant> {
+        let global = *lock_rate_limit(&self.inner.global_rate_limit_until);
+        let bucket = *lock_rate_limit(&self.inner.bucket_reset_at);
+        global.max(bucket).filter(|at| *at > Instant::now())
+    }
+
+    /// Waits until the global rate limit (reported by a previous response) is over.
+    async fn wait_for_global_rate_limit(&self) {
+        let until = *lock_rate_limit(&self.inner.global_rate_limit_until);
+        if let Some(until) = until {
+            tokio::time::sleep_until(until).await;
+        }
+    }
+
+    /// Sends the request created by `make_request`, following redirects as configured by
+    /// `with_redirect_policy`. The request is created again for every redirect that repeats it.
+    async fn request_following_redirects<F>(&self, make_request: F) -> WebhookResult<Response<Body>>
+    where
+        F: Fn() -> WebhookResult<Request<Body>>,
+    {
+        let mut request = make_request()?;
+        let mut redirects = 0;
+        loop {
+            let uri = request.uri().clone();
+            let method = request.method().clone();
+            let response = self.inner.client.request(request).await?;
+            if self.inner.max_redirects == 0 || !response.status().is_redirection() {
+                return Ok(response);
+            }
+            let target = match redirect_target(&uri, &response) {
+                Some(target) => target,
+                None => return Ok(response),
+            };
+            if target.host() != uri.host() {
+                return Err(WebhookError::other(format!(
+                    "Redirect to a different host ({}) not followed, the request carries the webhook token",
+                    target.host().unwrap_or_default()
+                )));
+            }
+            if redirects == self.inner.max_redirects {
+                return Err(WebhookError::other(format!(
+                    "Too many redirects (more than {})",
+                    self.inner.max_redirects
+                )));
+            }
+            redirects += 1;
+            #[cfg(feature = "tracing")]
+            tracing::info!(status = response.status().as_u16(), "Following a redirect");
+
+            let status = response.status();
+            let repeats_request = status == StatusCode::TEMPORARY_REDIRECT
+                || status == StatusCode::PERMANENT_REDIRECT
+                || method == Method::GET
+                || method == Method::HEAD;
+            if !repeats_request {
+                return Err(WebhookError::other(format!(
+                    "Redirect ({}) of the {} request not followed, continuing with a GET would drop the request body",
+                    status, method
+                )));
+            }
+            request = make_request()?;
+            *request.method_mut() = method;
+            *request.uri_mut() = target;
+        }
+    }
+
+    /// The URL `get_information` fetches. For incoming webhooks, this is the execute URL itself.
+    ///
+    /// The URL contains the webhook token, see `info_url_redacted` for logging.
+    pub fn info_url(&self) -> &str {
+        &self.inner.url
+    }
+
+    /// Same as `info_url`, with the webhook token masked.
+    pub fn info_url_redacted(&self) -> String {
+        redact_url(self.info_url())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "webhook.get_information", skip_all, fields(url = %redact_url(&self.inner.url)))
+    )]
+    pub async fn get_information(&self) -> WebhookResult<Webhook> {
+        let _permit = self.acquire_permit().await?;
+        let response = self
+            .send_request(|| Ok(Request::get(Uri::from_str(self.info_url())?).body(Body::empty())?))
+            .await?;
+        #[cfg(feature = "tracing")]
+        tracing::info!(status = response.status.as_u16(), "Webhook information fetched");
+        if !response.status.is_success() {
+            return Err(api_error(response.status, &response.body));
+        }
+        let webhook = serde_json::from_slice(&response.body)?;
+
+        Ok(webhook)
+    }
+
+    /// Fetches the webhook information along with the names of its channel and guild.
+    ///
+    /// Reading channels and guilds is not possible with the webhook token, a bot token
+    /// (of a bot with access to the channel) is required.
+    pub async fn resolve_names(&self, bot_token: &str) -> WebhookResult<WebhookInfo> {
+        let webhook = self.get_information().await?;
+        let channel: NamedObject = self
+            .fetch_bot_object(&format!("channels/{}", webhook.channel_id), bot_token)
+            .await?;
+        let guild: NamedObject = self
+            .fetch_bot_object(&format!("guilds/{}", webhook.guild_id), bot_token)
+            .await?;
+
+        Ok(WebhookInfo {
+            webhook,
+            channel_name: channel.name,
+            guild_name: guild.name,
+        })
+    }
+
+    /// Checks that the tags applied to the message (see `Message::applied_tag`) are available
+    /// in the webhook's forum channel, so that the send does not fail. Messages without
+    /// applied tags are not checked.
+    ///
+    /// Reading the channel's tags is not possible with the webhook token, a bot token
+    /// (of a bot with access to the channel) is required.
+    pub async fn check_applied_tags(&self, message: &Message, bot_token: &str) -> WebhookResult<()> {
+        let applied_tags = match &message.applied_tags {
+            Some(applied_tags) if !applied_tags.is_empty() => applied_tags,
+            _ => return Ok(()),
+        };
+        let webhook = self.get_information().await?;
+        let channel: ForumChannel = self
+            .fetch_bot_object(&format!("channels/{}", webhook.channel_id), bot_token)
+            .await?;
+        for tag in applied_tags.iter() {
+            if !channel.available_tags.iter().any(|available| &available.id == tag) {
+                return Err(WebhookError::Validation(ValidationError::Other(format!(
+                    "Applied tag ({}) is not available in the forum channel ({})!",
+                    tag, webhook.channel_id
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    async fn fetch_bot_object<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        bot_token: &str,
+    ) -> WebhookResult<T> {
+        let _permit = self.acquire_permit().await?;
+        let RawResponse { status, body, .. } = self.send_request(|| self.bot_request(path, bot_token)).await?;
+        if !status.is_success() {
+            return Err(WebhookError::Api {
+                status,
+                body: format!("Fetching {} failed: {}", path, String::from_utf8_lossy(&body)),
+            });
+        }
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Creates a GET request of the API endpoint `path` (relative to the API root of the webhook
+    /// URL) authorized by the bot token.
+    fn bot_request(&self, path: &str, bot_token: &str) -> WebhookResult<Request<Body>> {
+        let api_root = match self.inner.url.find("/webhooks/") {
+            Some(position) => &self.inner.url[..position],
+            None => {
+                return Err(WebhookError::InvalidUrl(
+                    "Cannot derive the API URL, the URL is not a webhook URL".to_string(),
+                ))
+            }
+        };
+        Ok(Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}/{}", api_root, path))
+            .header("authorization", format!("Bot {}", bot_token))
+            .body(Body::empty())?)
+    }
+}
+
+/// A Discord object (channel, guild) of which only the name is of interest.
+#[derive(serde::Deserialize)]
+struct NamedObject {
+    name: Option<String>,
+}
+
+/// A forum channel, of which only the available tags are of interest.
+#[derive(serde::Deserialize)]
+struct ForumChannel {
+    #[serde(default)]
+    available_tags: Vec<ForumTag>,
+}
+
+#[derive(serde::Deserialize)]
+struct ForumTag {
+    id: String,
+}
+
+/// Checks that the URL has the shape of a webhook execute URL, `.../api/webhooks/{id}/{token}`.
+fn check_webhook_url(url: &str) -> WebhookResult<()> {
+    let uri = Uri::from_str(url)?;
+    let segments: Vec<&str> = uri.path().split('/').collect();
+    let is_webhook_url = segments.contains(&"api")
+        && segments
+            .windows(3)
+            .any(|window| window[0] == "webhooks" && !window[1].is_empty() && !window[2].is_empty());
+    if is_webhook_url {
+        Ok(())
+    } else {
+        Err(WebhookError::InvalidUrl(format!(
+            "The URL ({}) is not a webhook URL, expected https://discord.com/api/webhooks/{{id}}/{{token}}",
+            redact_url(url)
+        )))
+    }
+}
+
+/// The URL of the message `message_id` sent by the webhook, the query of the webhook URL is kept
+/// and the `thread_id` (if there is a thread) is appended.
+fn message_url(url: &str, message_id: &str, thread_id: Option<&str>) -> String {
+    let url = match url.split_once('?') {
+        Some((base, query)) => format!("{}/messages/{}?{}", base, message_id, query),
+        None => format!("{}/messages/{}", url, message_id),
+    };
+    execute_url(&url, thread_id, &[])
+}
+
+/// Appends the `thread_id` (if there is a thread) and the other query `params` to the webhook
+/// URL, the names and values are percent-encoded.
+fn execute_url(url: &str, thread_id: Option<&str>, params: &[(&str, &str)]) -> String {
+    let mut url = url.to_string();
+    let thread_param = thread_id.map(|thread_id| ("thread_id", thread_id));
+    for (name, value) in thread_param.iter().chain(params.iter()) {
+        url.push(if url.contains('?') { '&' } else { '?' });
+        url.push_str(&percent_encode(name));
+        url.push('=');
+        url.push_str(&percent_encode(value));
+    }
+    url
+}
+
+/// Percent-encodes all but the unreserved characters of RFC 3986.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// A fully read response.
+struct RawResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// Removes the control characters other than newlines and tabs from all strings of `value`.
+fn strip_control_chars(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => text.retain(|c| !c.is_control() || c == '\n' || c == '\t'),
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_control_chars),
+        serde_json::Value::Object(map) => map.values_mut().for_each(strip_control_chars),
+        _ => {}
+    }
+}
+
+/// Locks the rate limit state. A panic while it was locked does not matter, the state is a single
+/// instant, so the lock is used even if it is poisoned.
+fn lock_rate_limit(state: &Mutex<Option<Instant>>) -> MutexGuard<'_, Option<Instant>> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Creates an error carrying the status and the error message (body) of a failed Discord
+/// API request.
+fn api_error(status: StatusCode, body: &[u8]) -> WebhookError {
+    let body = match String::from_utf8(body.to_vec()) {
+        Ok(msg) => msg,
+        Err(err) => "Error reading Discord API error message:".to_string() + &err.to_string(),
+    };
+    WebhookError::Api { status, body }
+}
+
+/// The error of a request for the message `message_id` answered by `404 Not Found`.
+fn unknown_message_error(message_id: &str) -> WebhookError {
+    WebhookError::Api {
+        status: StatusCode::NOT_FOUND,
+        body: format!(
+            "The message ({}) was not found, it does not exist (anymore) or was not sent by the webhook",
+            message_id
+        ),
+    }
+}
+
+/// A value unique to a send, see `Message::nonce`.
+fn nonce() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos())
+        .to_string()
+}
+
+/// The URI a redirect response points to, relative locations are resolved against `uri`.
+fn redirect_target(uri: &Uri, response: &Response<Body>) -> Option<Uri> {
+    let location = Uri::from_str(response.headers().get("location")?.to_str().ok()?).ok()?;
+    if location.scheme().is_some() {
+        return Some(location);
+    }
+    let mut target = Uri::builder().path_and_query(location.path_and_query()?.clone());
+    if let Some(scheme) = uri.scheme() {
+        target = target.scheme(scheme.clone());
+    }
+    if let Some(authority) = uri.authority() {
+        target = target.authority(authority.clone());
+    }
+    target.build().ok()
+}
+
+/// The URL with the deprecated `discordapp.com` host (or a subdomain of it) replaced by
+/// `discord.com`, None if the URL does not use the deprecated host.
+fn deprecated_host_normalized(url: &str) -> Option<String> {
+    let uri = Uri::from_str(url).ok()?;
+    let host = uri.host()?;
+    let subdomain = host.strip_suffix("discordapp.com")?;
+    if !subdomain.is_empty() && !subdomain.ends_with('.') {
+        return None;
+    }
+    let host_start = url.find(host)?;
+    Some(format!(
+        "{}{}discord.com{}",
+        &url[..host_start],
+        subdomain,
+        &url[host_start + host.len()..]
+    ))
+}
+
+/// Replaces the token of a webhook URL (`.../webhooks/{id}/{token}...`) by `***`.
+fn redact_url(url: &str) -> String {
+    const WEBHOOKS_SEGMENT: &str = "/webhooks/";
+    let id_start = match url.find(WEBHOOKS_SEGMENT) {
+        Some(position) => position + WEBHOOKS_SEGMENT.len(),
+        None => return url.to_string(),
+    };
+    let token_start = match url[id_start..].find('/') {
+        Some(position) => id_start + position + 1,
+        None => return url.to_string(),
+    };
+    let token_end = url[token_start..]
+        .find(['/', '?'])
+        .map_or(url.len(), |position| token_start + position);
+    format!("{}***{}", &url[..token_start], &url[token_end..])
+}
+
+fn multipart_boundary() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    format!("webhook-rs-boundary-{:x}", nanos)
+}
+
+/// Builds a `multipart/form-data` body with the JSON payload in the `payload_json` part
+/// and the attachments in the `files[{index}]` parts.
+///
+/// Streamed attachments are read only while the body is being sent.
+fn multipart_body(
+    boundary: &str,
+    payload: &str,
+    attachments: &[Attachment],
+) -> WebhookResult<Body> {
+    let mut parts: Vec<MultipartChunk> = vec![];
+    let mut buffer: Vec<u8> = vec![];
+    buffer.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"payload_json\"\r\n\
+            Content-Type: application/json\r\n\r\n{}\r\n",
+            boundary, payload
+        )
+        .as_bytes(),
+    );
+    for (index, attachment) in attachments.iter().enumerate() {
+        let filename: String = attachment
+            .filename
+            .chars()
+            .filter(|c| !c.is_control())
+            .map(|c| if c == '"' { '\'' } else { c })
+            .collect();
+        buffer.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"files[{}]\"; filename=\"{}\"\r\n\
+                Content-Type: application/octet-stream\r\n\r\n",
+                boundary, index, filename
+            )
+            .as_bytes(),
+        );
+        match &attachment.data {
+            AttachmentData::Bytes(data) => buffer.extend_from_slice(data),
+            AttachmentData::Stream(stream) => {
+                let reader = stream.take_reader().ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("Attachment {} has already been sent!", attachment.filename),
+                    )
+                })?;
+                parts.push(MultipartChunk::Bytes(Bytes::from(std::mem::take(&mut buffer))));
+                parts.push(MultipartChunk::Reader(reader));
+            }
+        }
+        buffer.extend_from_slice(b"\r\n");
+    }
+    buffer.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    if parts.is_empty() {
+        return Ok(Body::from(buffer));
+    }
+    parts.push(MultipartChunk::Bytes(Bytes::from(buffer)));
+    Ok(Body::wrap_stream(stream::iter(parts).flat_map(
+        |chunk| match chunk {
+            MultipartChunk::Bytes(bytes) => stream::once(future::ready(Ok(bytes))).boxed(),
+            MultipartChunk::Reader(reader) => reader_stream(reader).boxed(),
+        },
+    )))
+}
+
+enum MultipartChunk {
+    Bytes(Bytes),
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+}
+
+/// Reads `reader` in chunks, ends after the first error.
+fn reader_stream(
+    reader: Pin<Box<dyn AsyncRead + Send>>,
+) -> impl Stream<Item = std::io::Result<Bytes>> + Send {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    stream::unfold(Some(reader), |reader| async move {
+        let mut reader = reader?;
+        let mut chunk = vec![0; CHUNK_SIZE];
+        match reader.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(read) => {
+                chunk.truncate(read);
+                Some((Ok(Bytes::from(chunk)), Some(reader)))
+            }
+            Err(err) => Some((Err(err), None)),
+        }
+    })
+}
+
+/// Runs `future`, failing with a `TimedOut` error if it does not finish within `timeout`.
+async fn with_timeout<Type, Fut>(timeout: Option<Duration>, future: Fut) -> WebhookResult<Type>
+where
+    Fut: Future<Output = WebhookResult<Type>>,
+{
+    match timeout {
+        None => future.await,
+        Some(timeout) => match tokio::time::timeout(timeout, future).await {
+            Ok(result) => result,
+            Err(_) => Err(WebhookError::Timeout(timeout)),
+        },
+    }
+}
+
+#[cfg(test)]
+// tests assert that the constant limits they rely on were not changed
+#[allow(clippy::assertions_on_constants)]
+mod tests {
+    use crate::client::{multipart_body, redact_url, WebhookClient};
+    use crate::error::WebhookError;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, ReadBuf};
+    use crate::mock::{response, MockServer, RecordedRequest};
+    use proptest::prelude::*;
+    use crate::rate_limit::RetryPolicy;
+    use hyper::{Method, StatusCode};
+    use std::time::Duration;
+    use crate::models::{ActionRow, all_limits, AllowedMention, AllowedMentions, CustomId, DiscordApiCompatible, EditMessage, Embed, EmbedAuthor, EmbedField, EmbedFooter, escape_markdown, interval_check, Interval, LimitProfile, Limits, LinkButton, Message, MessageContext, NonLinkButtonStyle, RegularButton, SelectMenu, SelectOption, Token, ValidationError, ValidationTarget, ValidationWarning, WarningConfig, Webhook, WebhookKind};
+
+    fn assert_message_error<BuildFunc, MessagePred>(
+        message_build: BuildFunc,
+        msg_pred: MessagePred,
+    )
+    where
+        BuildFunc: Fn(&mut Message) -> &mut Message,
+        MessagePred: Fn(&str) -> bool,
+    {
+        let mut message = Message::new();
+        message_build(&mut message);
+        match message.check_compatibility(&mut MessageContext::new()) {
+            Err(err) => {
+                assert!(
+                    msg_pred(&err.to_string()),
+                    "Unexpected error message {}",
+                    err
+                )
+            }
+            Ok(_) => panic!("Error is expected"),
+        };
+    }
+
+    fn contains_all_predicate(needles: Vec<&str>) -> Box<dyn Fn(&str) -> bool> {
+        let owned_needles: Vec<String> = needles.iter().map(|n| n.to_string()).collect();
+        Box::new(move |haystack| {
+            let lower_haystack = haystack.to_lowercase();
+            owned_needles
+                .iter()
+                .all(|needle| lower_haystack.contains(needle))
+        })
+    }
+
+    fn assert_valid_message<BuildFunc>(func: BuildFunc)
+    where
+        BuildFunc: Fn(&mut Message) -> &mut Message,
+    {
+        let mut message = Message::new();
+        func(&mut message);
+        if let Err(unexpected) = message.check_compatibility(&mut MessageContext::new()) {
+            panic!("Unexpected validation error {}", unexpected);
+        }
+    }
+
+    #[test]
+    fn empty_action_row_prohibited() {
+        assert_message_error(
+            |message| message.action_row(|row| row),
+            contains_all_predicate(vec!["action row", "empty"]),
+        );
+    }
+
+    #[test]
+    fn send_message_custom_id_reuse_prohibited() {
         assert_message_error(
             |message| {
                 message.action_row(|row| {
-                    for i in 0..(ActionRow::BUTTON_COUNT_INTERVAL.max_allowed + 1) {
-                        row.regular_button(|btn| {
-                            btn.style(NonLinkButtonStyle::Primary)
-                                .custom_id(&(i.to_string()))
-                        });
-                    }
-                    row
+                    row.regular_button(|button| {
+                        button.custom_id("0").style(NonLinkButtonStyle::Primary)
+                    })
+                    .regular_button(|button| {
+                        button.custom_id("0").style(NonLinkButtonStyle::Primary)
+                    })
                 })
             },
-            contains_all_predicate(vec!["interval", "button"]),
+            contains_all_predicate(vec!["twice"]),
+        );
+    }
+
+    #[test]
+    fn send_message_custom_id_reuse_prohibited_across_action_rows() {
+        assert_message_error(
+            |message| {
+                message
+                    .action_row(|row| {
+                        row.regular_button(|button| {
+                            button.custom_id("0").style(NonLinkButtonStyle::Primary)
+                        })
+                    })
+                    .action_row(|row| {
+                        row.regular_button(|button| {
+                            button.custom_id("0").style(NonLinkButtonStyle::Primary)
+                        })
+                    })
+            },
+            contains_all_predicate(vec!["twice"]),
         );
     }
 
+    #[test] fn send_message_button_style_required() {
+        assert_message_error(
+            |message| message.action_row(|row| row.regular_button(|button| button.custom_id("0"))),
+            contains_all_predicate(vec!["style"]),
+        );
+    }
+
+    #[test]
+    fn send_message_url_required() {
+        assert_message_error(
+            |message| message.action_row(|row| row.link_button(|button| button.label("test"))),
+            contains_all_predicate(vec!["url"]),
+        );
+    }
+
+    #[test]
+    fn send_message_max_action_rows_enforced() {
+        assert_message_error(
+            |message| {
+                for _ in 0..(Message::ACTION_ROW_COUNT_INTERVAL.max_allowed + 1) {
+                    message.action_row(|row| row);
+                }
+                message
+            },
+            contains_all_predicate(vec!["interval", "row"]),
+        );
+    }
+
+    #[test]
+    fn send_message_max_label_len_enforced() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.regular_button(|btn| {
+                        btn.style(NonLinkButtonStyle::Primary)
+                            .custom_id("a")
+                            .label(&"l".repeat(Message::LABEL_LEN_INTERVAL.max_allowed + 1))
+                    })
+                })
+            },
+            contains_all_predicate(vec!["interval", "label"]),
+        );
+    }
+
+    #[test]
+    fn send_message_custom_id_required() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary))
+                })
+            },
+            contains_all_predicate(vec!["custom id"]),
+        );
+    }
+
+    #[test]
+    fn send_message_max_custom_id_len_enforced() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.regular_button(|btn| {
+                        btn.style(NonLinkButtonStyle::Primary).custom_id(
+                            &"a".repeat(Message::CUSTOM_ID_LEN_INTERVAL.max_allowed + 1),
+                        )
+                    })
+                })
+            },
+            contains_all_predicate(vec!["interval", "custom id"]),
+        );
+    }
+
+    #[test]
+    fn max_button_count_enforced() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    for i in 0..(ActionRow::BUTTON_COUNT_INTERVAL.max_allowed + 1) {
+                        row.regular_button(|btn| {
+                            btn.style(NonLinkButtonStyle::Primary)
+                                .custom_id(&(i.to_string()))
+                        });
+                    }
+                    row
+                })
+            },
+            contains_all_predicate(vec!["interval", "button"]),
+        );
+    }
+
+    #[test]
+    fn max_button_count_enforced_only_per_action_row() {
+        assert_valid_message(|message| {
+            for i in 0..Message::ACTION_ROW_COUNT_INTERVAL.max_allowed {
+                message.action_row(|row| {
+                    for j in 0..(ActionRow::BUTTON_COUNT_INTERVAL.max_allowed) {
+                        row.regular_button(|btn| {
+                            btn.style(NonLinkButtonStyle::Primary)
+                                .custom_id(&(i.to_string() + &j.to_string()))
+                        });
+                    }
+                    row
+                });
+            }
+            message
+        });
+    }
+
+    #[test]
+    fn message_valid_basic() {
+        assert_valid_message(|message| {
+            message
+                .content("@test")
+                .username("test")
+                .avatar_url("test")
+                .embed(|embed| {
+                    embed
+                        .title("test")
+                        .description("test")
+                        .footer("test", Some(String::from("https://example.com/test.png")))
+                        .image("test")
+                        .thumbnail("test")
+                        .author(
+                            "test",
+                            Some(String::from("test")),
+                            Some(String::from("https://example.com/test.png")),
+                        )
+                        .field("test", "test", false)
+                })
+        });
+    }
+
+    #[test]
+    fn embed_title_len_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .title(&"a".repeat(Embed::TITLE_LEN_INTERVAL.max_allowed + 1))
+                })
+        },
+     contains_all_predicate(vec!["interval", "embed", "title", "length"]),
+        )
+    }
+
+    #[test]
+    fn embed_description_len_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .description(&"a".repeat(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed + 1))
+                })
+        },
+     contains_all_predicate(vec!["interval", "embed", "description", "length"]),
+        )
+    }
+
+    #[test]
+    fn embed_author_name_len_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .author(&"a".repeat(EmbedAuthor::NAME_LEN_INTERVAL.max_allowed + 1), None, None)
+                })
+        },
+         contains_all_predicate(vec!["interval", "embed", "author", "name", "length"]),
+        )
+    }
+
+    #[test]
+    fn embed_footer_text_len_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .footer(&"a".repeat(EmbedFooter::TEXT_LEN_INTERVAL.max_allowed + 1), None)
+                })
+        },
+         contains_all_predicate(vec!["interval", "embed", "footer", "text", "length"]),
+        )
+    }
+
+    #[test]
+    fn embed_field_name_len_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .field(&"a".repeat(EmbedField::NAME_LEN_INTERVAL.max_allowed + 1), "None", false)
+                })
+        },
+         contains_all_predicate(vec!["interval", "embed", "field", "name", "length"]),
+        )
+    }
+
+    #[test]
+    fn embed_field_chunked() {
+        let mut embed = Embed::new();
+        embed.field_chunked("log", &"a".repeat(3000), false).unwrap();
+        let lengths: Vec<usize> = embed.fields.iter().map(|field| field.value.len()).collect();
+        assert_eq!(lengths, vec![1024, 1024, 952]);
+        assert_eq!(embed.fields[0].name, "log");
+        assert_eq!(embed.fields[2].name, "log (cont.)");
+
+        let line = format!("{}\n", "b".repeat(99));
+        let mut embed = Embed::new();
+        embed.field_chunked("lines", &line.repeat(30), true).unwrap();
+        assert_eq!(embed.fields.len(), 3);
+        assert!(embed.fields.iter().all(|field| field.value.len() <= 1024 && field.value.starts_with('b')));
+
+        let mut embed = Embed::new();
+        let err = embed.field_chunked("huge", &"c".repeat(26 * 1024), false).unwrap_err();
+        assert!(contains_all_predicate(vec!["embed field count", "interval"])(&err.to_string()));
+        assert!(embed.fields.is_empty());
+    }
+
+    #[test]
+    fn embed_field_value_len_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .field("None", &"a".repeat(EmbedField::VALUE_LEN_INTERVAL.max_allowed + 1), false)
+                })
+        },
+     contains_all_predicate(vec!["interval", "embed", "field", "value", "length"]),
+        )
+    }
+
+    #[test]
+    fn embed_total_char_length_enforced() {
+        // adds 2 embeds with maximum length descriptions
+        // which should overflow the maximum allowed characters for embeds in total
+        assert!(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed * 2 > Message::EMBED_TOTAL_TEXT_LEN_INTERVAL.max_allowed, "Key test values modified, fix this test!");
+
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .description(&"a".repeat(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed))
+                })
+                .embed(|embed| {
+                    embed
+                        .description(&"a".repeat(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed))
+                })
+        },
+         contains_all_predicate(vec!["interval", "character", "count", "embed"]),
+        )
+    }
+
+    #[test]
+    fn embed_count_enforced() {
+        assert_message_error(|message| {
+            for _ in 0..Message::EMBED_COUNT_INTERVAL.max_allowed + 1 {
+                message.embed(|embed| embed.title("title"));
+            }
+            message
+        },
+         contains_all_predicate(vec!["interval", "embed", "count"]),
+        )
+    }
+
+    #[test]
+    fn field_count_enforced() {
+        assert_message_error(|message| {
+            message
+                .embed(|embed| {
+                    for _ in 0..Embed::FIELDS_LEN_INTERVAL.max_allowed + 1 {
+                        embed.field("None", "a", false);
+                    }
+                    embed
+                })
+        },
+         contains_all_predicate(vec!["interval", "embed", "field", "count"]),
+        )
+    }
+
+    #[test]
+    fn select_menu_valid() {
+        assert_valid_message(|message| {
+            message.action_row(|row| {
+                row.select_menu(|menu| {
+                    menu.custom_id("menu")
+                        .min_values(1)
+                        .max_values(2)
+                        .option(|opt| opt.label("a").value("a"))
+                        .option(|opt| opt.label("b").value("b"))
+                })
+            })
+        });
+    }
+
+    #[test]
+    fn select_menu_option_count_enforced() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.select_menu(|menu| {
+                        for i in 0..(SelectMenu::OPTION_COUNT_INTERVAL.max_allowed + 1) {
+                            menu.option(|opt| opt.label("a").value(&i.to_string()));
+                        }
+                        menu.custom_id("menu")
+                    })
+                })
+            },
+            contains_all_predicate(vec!["interval", "option count"]),
+        );
+    }
+
+    #[test]
+    fn select_menu_cannot_share_row_with_button() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("a"))
+                        .select_menu(|menu| menu.custom_id("b").option(|opt| opt.label("a").value("a")))
+                })
+            },
+            contains_all_predicate(vec!["select menu", "action row"]),
+        );
+    }
+
+    #[test]
+    fn select_menus_in_separate_rows_allowed() {
+        assert_valid_message(|message| {
+            message
+                .action_row(|row| row.select_menu(|menu| menu.custom_id("first").option(|opt| opt.label("a").value("a"))))
+                .action_row(|row| row.select_menu(|menu| menu.custom_id("second").option(|opt| opt.label("b").value("b"))))
+                .action_row(|row| row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("button")))
+        });
+    }
+
+    #[test]
+    fn select_menu_min_values_over_default_max_prohibited() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.select_menu(|menu| {
+                        menu.custom_id("menu")
+                            .min_values(3)
+                            .option(|opt| opt.label("a").value("a"))
+                            .option(|opt| opt.label("b").value("b"))
+                            .option(|opt| opt.label("c").value("c"))
+                    })
+                })
+            },
+            contains_all_predicate(vec!["min values", "max values"]),
+        );
+    }
+
+    #[test]
+    fn select_menu_min_values_on_single_select_prohibited() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.select_menu(|menu| {
+                        menu.custom_id("menu")
+                            .min_values(2)
+                            .option(|opt| opt.label("a").value("a"))
+                            .option(|opt| opt.label("b").value("b"))
+                    })
+                })
+            },
+            contains_all_predicate(vec!["min values (2)", "single-select"]),
+        );
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.select_menu(|menu| {
+                        menu.custom_id("menu")
+                            .min_values(3)
+                            .max_values(2)
+                            .option(|opt| opt.label("a").value("a"))
+                            .option(|opt| opt.label("b").value("b"))
+                            .option(|opt| opt.label("c").value("c"))
+                    })
+                })
+            },
+            |err| !contains_all_predicate(vec!["single-select"])(err),
+        );
+    }
+
+    #[test]
+    fn select_option_emoji_conflict_in_menu_prohibited() {
+        assert_message_error(
+            |message| {
+                message.action_row(|row| {
+                    row.select_menu(|menu| {
+                        menu.custom_id("menu")
+                            .option(|opt| opt.label("a").value("a").emoji("625891304081063986", "mage", false))
+                            .option(|opt| opt.label("b").value("b").emoji("625891304081063986", "mage", true))
+                    })
+                })
+            },
+            contains_all_predicate(vec!["625891304081063986", "animated", "select menu (menu)"]),
+        );
+    }
+
+    #[test]
+    fn emoji_animated_flag_conflict_prohibited() {
+        assert_message_error(
+            |message| {
+                message
+                    .action_row(|row| {
+                        row.regular_button(|btn| {
+                            btn.style(NonLinkButtonStyle::Primary)
+                                .custom_id("a")
+                                .emoji("625891304081063986", "mage", false)
+                        })
+                    })
+                    .action_row(|row| {
+                        row.select_menu(|menu| {
+                            menu.custom_id("b").option(|opt| {
+                                opt.label("a")
+                                    .value("a")
+                                    .emoji("625891304081063986", "mage", true)
+                            })
+                        })
+                    })
+            },
+            contains_all_predicate(vec!["emoji", "625891304081063986", "animated"]),
+        );
+    }
+
+    #[test]
+    fn emoji_reuse_allowed() {
+        assert_valid_message(|message| {
+            message.action_row(|row| {
+                row.regular_button(|btn| {
+                    btn.style(NonLinkButtonStyle::Primary)
+                        .custom_id("a")
+                        .emoji("625891304081063986", "mage", true)
+                })
+                .link_button(|btn| {
+                    btn.url("https://discord.com")
+                        .emoji("625891304081063986", "mage", true)
+                })
+            })
+        });
+    }
+
+    #[test]
+    fn plaintext_preview_contains_content_and_embed_title() {
+        let mut message = Message::new();
+        message
+            .content("Hello")
+            .embed(|embed| embed.title("Embed title").field("name", "value", false))
+            .action_row(|row| {
+                row.regular_button(|btn| {
+                    btn.style(NonLinkButtonStyle::Primary)
+                        .custom_id("a")
+                        .label("Click")
+                })
+            });
+
+        let preview = message.to_plaintext();
+        assert!(preview.contains("Hello"));
+        assert!(preview.contains("Embed title"));
+        assert!(preview.contains("name: value"));
+        assert!(preview.contains("Click"));
+    }
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn rustls_client_construction() {
+        let client = WebhookClient::new("https://discord.com/api/webhooks/0/token");
+        test_is_send(client);
+    }
+
+    #[test]
+    fn fields_added_to_last_embed() {
+        let mut message = Message::new();
+        message.embed(|embed| embed.title("first")).embed(|embed| embed.title("last"));
+        for i in 0..3 {
+            message
+                .add_field_to_last_embed(&i.to_string(), "value", false)
+                .unwrap();
+        }
+
+        assert!(message.embeds[0].fields.is_empty());
+        let names: Vec<&str> = message.embeds[1].fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["0", "1", "2"]);
+    }
+
+    #[test]
+    fn embed_mutated_by_index() {
+        let mut message = Message::new();
+        message.embed(|embed| embed.description("0%"));
+        message.embed_mut(0).unwrap().description("50%");
+
+        assert_eq!(message.embeds[0].description.as_deref(), Some("50%"));
+        assert!(message.embed_mut(1).is_none());
+    }
+
+    #[test]
+    fn field_added_to_last_embed_requires_embed() {
+        let mut message = Message::new();
+        assert!(message.add_field_to_last_embed("name", "value", false).is_err());
+    }
+
+    #[test]
+    fn field_added_to_last_embed_count_enforced() {
+        let mut message = Message::new();
+        message.embed(|embed| embed);
+        for _ in 0..Embed::FIELDS_LEN_INTERVAL.max_allowed {
+            message.add_field_to_last_embed("name", "value", false).unwrap();
+        }
+        let err = message.add_field_to_last_embed("name", "value", false).unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "field count"])(&err.to_string()));
+    }
+
+    #[test]
+    fn custom_id_round_trip() {
+        let id = CustomId::new("vote").arg("yes").arg("msg123").build().unwrap();
+        assert_eq!(id, "vote:yes:msg123");
+        assert_valid_message(|message| {
+            message.action_row(|row| {
+                row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id(&id))
+            })
+        });
+
+        let parsed = CustomId::parse(&id).unwrap();
+        assert_eq!(parsed, *CustomId::new("vote").arg("yes").arg("msg123"));
+    }
+
+    #[test]
+    fn custom_id_len_enforced() {
+        let err = CustomId::new("prefix")
+            .arg(&"a".repeat(Message::CUSTOM_ID_LEN_INTERVAL.max_allowed))
+            .build()
+            .unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "custom id"])(&err.to_string()));
+
+        let too_long = "a".repeat(Message::CUSTOM_ID_LEN_INTERVAL.max_allowed + 1);
+        assert!(CustomId::parse(&too_long).is_err());
+    }
+
+    #[test]
+    fn custom_id_segment_separator_prohibited() {
+        let err = CustomId::new("vote").arg("a:b").build().unwrap_err();
+        assert!(contains_all_predicate(vec!["separator"])(&err.to_string()));
+    }
+
+    #[test]
+    fn custom_interval_validation() {
+        // a policy stricter than Discord's content limits
+        let policy = Interval::from_min_max(1, 10);
+        assert!(interval_check(&policy, &5, "Policy").is_ok());
+        let err = interval_check(&policy, &11, "Policy").unwrap_err();
+        assert!(contains_all_predicate(vec!["policy", "11", "[1, 10]"])(&err.to_string()));
+
+        assert_eq!(policy.clamp(0), 1);
+        assert_eq!(policy.span(), 9);
+        assert_eq!(
+            policy.intersection(&Message::LABEL_LEN_INTERVAL),
+            Some(Interval::from_min_max(1, 10))
+        );
+        assert_eq!(policy.intersection(&Interval::from_min_max(11, 20)), None);
+    }
+
+    #[test]
+    fn interval_contains_const() {
+        const _: () = assert!(Message::CONTENT_LEN_INTERVAL.contains_const(2000));
+        const _: () = assert!(!Message::CONTENT_LEN_INTERVAL.contains_const(2001));
+        assert!(Interval::from_min_max(1u8, 5).contains_const(1));
+    }
+
+    #[test]
+    fn edit_message_omits_unset_fields() {
+        let mut edit = EditMessage::new();
+        edit.content("edited");
+        let json: serde_json::Value = serde_json::to_value(&edit).unwrap();
+        assert_eq!(json, serde_json::json!({ "content": "edited" }));
+    }
+
+    #[test]
+    fn edit_message_clears_components() {
+        let mut edit = EditMessage::new();
+        edit.action_row(|row| {
+            row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("a"))
+        })
+        .clear_action_rows()
+        .clear_embeds();
+        assert!(edit.check_compatibility(&mut MessageContext::new()).is_ok());
+
+        let json: serde_json::Value = serde_json::to_value(&edit).unwrap();
+        assert_eq!(json, serde_json::json!({ "components": [], "embeds": [] }));
+    }
+
+    #[tokio::test]
+    async fn cloned_clients_send_concurrently() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let clone = client.clone();
+
+        let first = tokio::spawn(async move { client.send(|message| message.content("a")).await });
+        let second = tokio::spawn(async move { clone.send(|message| message.content("b")).await });
+        assert!(first.await.unwrap().unwrap());
+        assert!(second.await.unwrap().unwrap());
+
+        let requests = server.requests();
+        assert!(requests.iter().all(|request| request.method == Method::POST
+            && request.uri.path() == "/api/webhooks/1234/token"
+            && request.headers["content-type"] == "application/json"));
+        let mut contents: Vec<String> = requests
+            .iter()
+            .map(|request| request.body_json()["content"].as_str().unwrap().to_string())
+            .collect();
+        contents.sort();
+        assert_eq!(contents, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn per_call_timeout_takes_precedence() {
+        let server = MockServer::start_with_delay(Duration::from_millis(300), |_, _| {
+            response(StatusCode::NO_CONTENT, "")
+        });
+
+        let patient_client = WebhookClient::new(&server.url).with_timeout(Duration::from_secs(10));
+        let err = patient_client
+            .send_with_timeout(|message| message.content("a"), Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(contains_all_predicate(vec!["timed out"])(&err.to_string()));
+
+        let impatient_client = WebhookClient::new(&server.url).with_timeout(Duration::from_millis(50));
+        assert!(impatient_client.send(|message| message.content("a")).await.is_err());
+        assert!(impatient_client
+            .send_with_timeout(|message| message.content("a"), Duration::from_secs(10))
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn attachment_count_enforced() {
+        assert_message_error(
+            |message| {
+                for i in 0..(Message::ATTACHMENT_COUNT_INTERVAL.max_allowed + 1) {
+                    message.attachment(&format!("{}.txt", i), vec![0]);
+                }
+                message
+            },
+            contains_all_predicate(vec!["interval", "attachment count"]),
+        );
+    }
+
+    #[test]
+    fn attachment_combined_size_enforced() {
+        let half_limit = LimitProfile::Default.max_upload_size() / 2;
+        assert_message_error(
+            |message| {
+                message
+                    .attachment("a.bin", vec![0; half_limit])
+                    .attachment("b.bin", vec![0; half_limit + 1])
+            },
+            contains_all_predicate(vec!["interval", "combined attachment size"]),
+        );
+    }
+
+    #[tokio::test]
+    async fn attachments_sent_as_multipart() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        client
+            .send(|message| message.content("file").attachment("a.txt", b"hello".to_vec()))
+            .await
+            .unwrap();
+
+        let request = &server.requests()[0];
+        let content_type = request.headers["content-type"].to_str().unwrap();
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+        let body = String::from_utf8(request.body.clone()).unwrap();
+        assert!(body.contains(r#"name="payload_json""#));
+        assert!(body.contains(r#""attachments":[{"id":0,"filename":"a.txt","description":null}]"#));
+        assert!(body.contains(r#"name="files[0]"; filename="a.txt""#));
+        assert!(body.contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn oversized_attachments_rejected_before_sending() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        for i in 0..(Message::ATTACHMENT_COUNT_INTERVAL.max_allowed + 1) {
+            message.attachment(&format!("{}.txt", i), vec![0]);
+        }
+
+        assert!(client.send_message(&message).await.is_err());
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn confirm_cancel_row() {
+        let row = ActionRow::confirm_cancel("yes", "no").unwrap();
+        let json = serde_json::to_value(&row).unwrap();
+        let buttons = json["components"].as_array().unwrap();
+        assert_eq!(buttons.len(), 2);
+        assert_eq!(buttons[0]["custom_id"], "yes");
+        assert_eq!(buttons[0]["style"], 3);
+        assert_eq!(buttons[0]["label"], "Confirm");
+        assert_eq!(buttons[1]["custom_id"], "no");
+        assert_eq!(buttons[1]["style"], 4);
+        assert_eq!(buttons[1]["label"], "Cancel");
+
+        assert_valid_message(|message| {
+            message.add_action_row(ActionRow::confirm_cancel("yes", "no").unwrap())
+        });
+    }
+
+    #[test]
+    fn fluent_and_closure_styles_equivalent() {
+        let mut closure_message = Message::new();
+        closure_message
+            .content("pick")
+            .embed(|embed| embed.title("title").field("name", "value", true))
+            .action_row(|row| {
+                row.regular_button(|button| {
+                    button.style(NonLinkButtonStyle::Primary).custom_id("ok").label("OK")
+                })
+                .link_button(|button| button.url("https://example.com").label("Docs"))
+            })
+            .action_row(|row| {
+                row.select_menu(|menu| {
+                    menu.custom_id("menu")
+                        .option(|option| option.label("A").value("a").default(true))
+                })
+            });
+
+        let mut embed = Embed::new();
+        embed.title("title").add_field(EmbedField::new("name", "value", true));
+        let mut button = RegularButton::new();
+        button.style(NonLinkButtonStyle::Primary).custom_id("ok").label("OK");
+        let mut link = LinkButton::new();
+        link.url("https://example.com").label("Docs");
+        let mut buttons = ActionRow::new();
+        buttons.add_regular_button(button).add_link_button(link);
+        let mut option = SelectOption::new();
+        option.label("A").value("a").default(true);
+        let mut menu = SelectMenu::new();
+        menu.custom_id("menu").add_option(option);
+        let mut menu_row = ActionRow::new();
+        menu_row.add_select_menu(menu);
+        let mut fluent_message = Message::new();
+        fluent_message
+            .content("pick")
+            .add_embed(embed)
+            .add_action_row(buttons)
+            .add_action_row(menu_row);
+
+        assert_eq!(
+            serde_json::to_value(&closure_message).unwrap(),
+            serde_json::to_value(&fluent_message).unwrap()
+        );
+    }
+
+    #[test]
+    fn built_embed_reused() {
+        let embed = Embed::build(|embed| embed.title("shared").field("name", "value", false));
+        let mut first = Message::new();
+        first.content("first").embeds(vec![embed.clone()]);
+        let mut second = Message::new();
+        second.content("second").embeds(vec![embed]);
+
+        let first = serde_json::to_value(&first).unwrap();
+        let second = serde_json::to_value(&second).unwrap();
+        assert_eq!(first["embeds"], second["embeds"]);
+        assert_eq!(first["embeds"][0]["title"], "shared");
+        assert_eq!(first["embeds"][0]["fields"][0]["name"], "name");
+
+        let menu = SelectMenu::build(|menu| menu.custom_id("menu").option(|o| o.label("A").value("a")));
+        assert_valid_message(|message| {
+            message.action_row(|row| row.add_select_menu(menu.clone()))
+        });
+    }
+
+    #[test]
+    fn remaining_embed_budget() {
+        let mut message = Message::new();
+        assert_eq!(message.remaining_embed_budget(), 6000);
+        message
+            .embed(|embed| embed.title("12345").footer("123", None))
+            .embed(|embed| embed.description("1234567890").field("ab", "cd", false));
+        assert_eq!(message.remaining_embed_budget(), 6000 - 22);
+
+        message.embed(|embed| embed.description(&"a".repeat(5990)));
+        assert_eq!(message.remaining_embed_budget(), -12);
+    }
+
+    #[test]
+    fn remove_action_row() {
+        let mut message = Message::new();
+        message
+            .action_row(|row| row.regular_button(|b| b.style(NonLinkButtonStyle::Primary).custom_id("first")))
+            .action_row(|row| row.regular_button(|b| b.style(NonLinkButtonStyle::Primary).custom_id("second")));
+        assert_eq!(message.action_row_count(), 2);
+
+        message.remove_action_row(0).unwrap();
+        assert_eq!(message.action_row_count(), 1);
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["components"][0]["components"][0]["custom_id"], "second");
+
+        let err = message.remove_action_row(1).unwrap_err();
+        assert!(contains_all_predicate(vec!["action row 1", "1 action rows"])(&err.to_string()));
+        assert_eq!(message.action_row_count(), 1);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn embed_timestamp_from_date_time() {
+        use chrono::TimeZone;
+        let timestamp = chrono::Utc.with_ymd_and_hms(2021, 12, 24, 18, 30, 5).unwrap();
+        let embed = Embed::build(|embed| embed.timestamp_dt(timestamp));
+        let json = serde_json::to_value(&embed).unwrap();
+        assert_eq!(json["timestamp"], "2021-12-24T18:30:05+00:00");
+    }
+
+    #[test]
+    fn replied_user_without_reference_prohibited() {
+        assert_message_error(
+            |message| message.content("hi").allow_mentions(None, None, None, true),
+            contains_all_predicate(vec!["replied_user", "message reference"]),
+        );
+        assert_valid_message(|message| message.content("hi").allow_mentions(None, None, None, false));
+        assert_valid_message(|message| message.content("hi").reply_to("42").allow_mentions(None, None, None, true));
+        let mut reply = Message::new();
+        reply.content("hi").reply_to("42");
+        assert_eq!(serde_json::to_value(&reply).unwrap()["message_reference"], serde_json::json!({"message_id": "42"}));
+
+        let mut edit = EditMessage::new();
+        edit.allow_mentions(None, None, None, true);
+        assert!(edit.check_compatibility(&mut MessageContext::new()).is_ok());
+    }
+
+    #[test]
+    fn limits_enumerated() {
+        let limits = all_limits();
+        assert!(limits.contains(&("content", 0, 2000)));
+        assert!(limits.contains(&("custom_id", 1, 100)));
+        assert!(limits.contains(&("select_menu_max_values", 1, 25)));
+        let mut names: Vec<_> = limits.iter().map(|(name, _, _)| name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), limits.len());
+    }
+
+    #[test]
+    fn select_menu_options_from_pairs() {
+        let menu = SelectMenu::build(|menu| {
+            menu.custom_id("color")
+                .options_from(&[("Red", "r"), ("Green", "g"), ("Blue", "b")])
+        });
+        let json = serde_json::to_value(&menu).unwrap();
+        let options = json["options"].as_array().unwrap();
+        assert_eq!(options.len(), 3);
+        assert_eq!(options[1]["label"], "Green");
+        assert_eq!(options[1]["value"], "g");
+        assert_valid_message(|message| message.action_row(|row| row.add_select_menu(menu.clone())));
+
+        let too_many: Vec<(String, String)> = (0..=SelectMenu::OPTION_COUNT_INTERVAL.max_allowed)
+            .map(|i| (i.to_string(), i.to_string()))
+            .collect();
+        let too_many: Vec<(&str, &str)> = too_many.iter().map(|(l, v)| (l.as_str(), v.as_str())).collect();
+        assert_message_error(
+            |message| message.action_row(|row| row.select_menu(|menu| menu.custom_id("m").options_from(&too_many))),
+            contains_all_predicate(vec!["option count"]),
+        );
+    }
+
+    #[test]
+    fn duplicate_button_labels_warned() {
+        let mut message = Message::new();
+        message
+            .action_row(|row| {
+                row.regular_button(|b| b.style(NonLinkButtonStyle::Primary).custom_id("a").label("Go"))
+                    .regular_button(|b| b.style(NonLinkButtonStyle::Primary).custom_id("b").label("Go"))
+                    .link_button(|b| b.url("https://example.com").label("Go"))
+            })
+            .action_row(|row| {
+                row.regular_button(|b| b.style(NonLinkButtonStyle::Primary).custom_id("c").label("Go"))
+            });
+
+        assert_eq!(
+            message.warnings(),
+            vec![ValidationWarning::DuplicateButtonLabel { row: 0, label: "Go".to_string() }]
+        );
+        // a warning does not block sending
+        assert!(message.check_all().is_empty());
+    }
+
+    #[test]
+    fn try_build_collects_errors() {
+        let errors = Message::try_build(|message| {
+            message
+                .content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1))
+                .action_row(|row| row)
+        })
+        .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(contains_all_predicate(vec!["content length"])(&errors[0].to_string()));
+        assert!(contains_all_predicate(vec!["action row", "empty"])(&errors[1].to_string()));
+
+        let message = Message::try_build(|message| message.content("fine")).unwrap();
+        assert_eq!(message.content.as_deref(), Some("fine"));
+    }
+
+    #[test]
+    fn long_embed_description_warned() {
+        let mut message = Message::new();
+        message
+            .embed(|embed| embed.description(&"a".repeat(2000)))
+            .embed(|embed| embed.description(&"a".repeat(3000)));
+        assert_eq!(
+            message.warnings(),
+            vec![ValidationWarning::LongEmbedDescription { embed: 1, length: 3000 }]
+        );
+        assert!(message.check_all().is_empty());
+
+        let config = WarningConfig { long_description_threshold: 3000 };
+        assert!(message.warnings_with(&config).is_empty());
+    }
+
+    #[test]
+    fn embed_attachment_references_checked() {
+        assert_message_error(
+            |message| {
+                message
+                    .attachment("chart.png", vec![1, 2, 3])
+                    .embed(|embed| embed.image("attachment://chart.png"))
+                    .embed(|embed| embed.thumbnail("attachment://logo.png"))
+            },
+            contains_all_predicate(vec!["embed 1", "logo.png", "not uploaded"]),
+        );
+        assert_valid_message(|message| {
+            message
+                .attachment("chart.png", vec![1, 2, 3])
+                .attachment("logo.png", vec![4, 5])
+                .embed(|embed| embed.image("attachment://chart.png"))
+                .embed(|embed| embed.thumbnail("attachment://logo.png").footer("f", Some("https://example.com/x.png".to_string())))
+        });
+    }
+
+    #[test]
+    fn allowed_mentions_presets() {
+        let mut message = Message::new();
+        message.content("@everyone <@&1> <@2>").allowed_mentions_preset(AllowedMentions::none());
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            json["allowed_mentions"],
+            serde_json::json!({"parse": [], "replied_user": false})
+        );
+
+        let json = serde_json::to_value(AllowedMentions::all()).unwrap();
+        assert_eq!(json["parse"], serde_json::json!(["roles", "users", "everyone"]));
+
+        let json = serde_json::to_value(AllowedMentions::only_users(vec!["2".to_string()])).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"parse": [], "users": ["2"], "replied_user": false})
+        );
+    }
+
+    #[test]
+    fn link_button_url_length_enforced() {
+        let url = format!("https://example.com/{}", "a".repeat(493));
+        assert_eq!(url.len(), 513);
+        assert_message_error(
+            |message| message.action_row(|row| row.link_button(|button| button.url(&url).label("x"))),
+            contains_all_predicate(vec!["url", "513"]),
+        );
+        assert_valid_message(|message| {
+            message.action_row(|row| row.link_button(|button| button.url(&url[..512]).label("x")))
+        });
+    }
+
+    #[test]
+    fn preflight_template() {
+        let mut template = Message::new();
+        template
+            .content("Deployment finished")
+            .username("CI")
+            .attachment("report.txt", b"all green".to_vec())
+            .embed(|embed| {
+                embed
+                    .title("Build #42")
+                    .thumbnail("attachment://report.txt")
+                    .field("Duration", "3m", true)
+                    .footer("ci", None)
+            })
+            .action_row(|row| row.link_button(|button| button.url("https://example.com/42").label("Logs")));
+        assert_eq!(template.preflight(), Ok(()));
+
+        let mut broken = Message::new();
+        broken
+            .attachment("huge.bin", vec![0; LimitProfile::Default.max_upload_size()])
+            .action_row(|row| row);
+        let violations = broken.preflight().unwrap_err();
+        // the attachment alone fits the limit, the whole request does not
+        assert_eq!(violations.len(), 2);
+        assert!(contains_all_predicate(vec!["action row", "empty"])(&violations[0].to_string()));
+        assert!(contains_all_predicate(vec!["request size"])(&violations[1].to_string()));
+    }
+
+    #[test]
+    fn markdown_escaped() {
+        assert_eq!(escape_markdown("**bold**"), r"\*\*bold\*\*");
+        assert_eq!(escape_markdown(r"a_b ~~c~~ ||d|| `e` \f"), r"a\_b \~\~c\~\~ \|\|d\|\| \`e\` \\f");
+        assert_eq!(escape_markdown("plain text"), "plain text");
+
+        let mut message = Message::new();
+        message.content_escaped("**bold**");
+        assert_eq!(message.content.as_deref(), Some(r"\*\*bold\*\*"));
+    }
+
+    #[test]
+    fn embed_icon_urls_validated() {
+        assert_message_error(
+            |message| message.embed(|embed| embed.author("author", None, Some("not a url".to_string()))),
+            contains_all_predicate(vec!["author icon url", "not a url"]),
+        );
+        assert_message_error(
+            |message| message.embed(|embed| embed.footer("footer", Some("ftp://example.com/a.png".to_string()))),
+            contains_all_predicate(vec!["footer icon url"]),
+        );
+        assert_valid_message(|message| {
+            message.embed(|embed| embed.footer("footer", Some("https://example.com/icon.png".to_string())))
+        });
+    }
+
+    #[test]
+    fn strict_footer_and_author_setters() {
+        let mut embed = Embed::new();
+        let err = embed.try_footer("footer", Some("icon".to_string())).unwrap_err();
+        assert!(contains_all_predicate(vec!["footer icon url", "icon"])(&err.to_string()));
+        assert!(embed.footer.is_none());
+        assert!(embed.try_author("author", None, Some("not a url".to_string())).is_err());
+        assert!(embed.author.is_none());
+
+        embed
+            .try_footer("footer", Some("https://example.com/icon.png".to_string()))
+            .unwrap()
+            .try_author("author", None, None)
+            .unwrap();
+        assert_eq!(embed.footer.unwrap().text, "footer");
+        assert_eq!(embed.author.unwrap().name, "author");
+    }
+
+    #[test]
+    fn content_hash_stable() {
+        let build = |content: &str, data: Vec<u8>| {
+            let mut message = Message::new();
+            message
+                .content(content)
+                .embed(|embed| embed.title("title"))
+                .attachment("a.txt", data);
+            message
+        };
+        let hash = build("same", vec![1, 2]).content_hash();
+        assert_eq!(hash, build("same", vec![1, 2]).content_hash());
+        assert_ne!(hash, build("other", vec![1, 2]).content_hash());
+        assert_ne!(hash, build("same", vec![1, 3]).content_hash());
+        assert_ne!(Message::new().content_hash(), hash);
+        // the hash must not change between runs or versions
+        assert_eq!(Message::new().content_hash(), 11733676817633719957);
+    }
+
+    #[test]
+    fn content_hash_includes_thread() {
+        let build = |thread_id: Option<&str>| {
+            let mut message = Message::new();
+            message.content("same");
+            if let Some(thread_id) = thread_id {
+                message.thread_id(thread_id);
+            }
+            message
+        };
+        let hash = build(Some("1")).content_hash();
+        assert_eq!(hash, build(Some("1")).content_hash());
+        assert_ne!(hash, build(Some("2")).content_hash());
+        assert_ne!(hash, build(None).content_hash());
+    }
+
+    #[test]
+    fn components_v2_excludes_content_and_embeds() {
+        assert_message_error(
+            |message| message.flag(Message::FLAG_IS_COMPONENTS_V2).content("old way"),
+            contains_all_predicate(vec!["components v2", "content or embeds"]),
+        );
+        assert_message_error(
+            |message| message.flag(Message::FLAG_IS_COMPONENTS_V2).embed(|embed| embed.title("old way")),
+            contains_all_predicate(vec!["components v2"]),
+        );
+
+        let mut message = Message::new();
+        message
+            .flag(Message::FLAG_SUPPRESS_NOTIFICATIONS)
+            .flag(Message::FLAG_IS_COMPONENTS_V2)
+            .action_row(|row| row.regular_button(|b| b.style(NonLinkButtonStyle::Primary).custom_id("v2")));
+        assert!(message.check_all().is_empty());
+        assert_eq!(serde_json::to_value(&message).unwrap()["flags"], (1 << 12) | (1 << 15));
+    }
+
+    #[test]
+    fn button_style_from_str() {
+        let mut button = RegularButton::new();
+        button.style_from_str("DaNgEr").unwrap().custom_id("id");
+        let mut row = ActionRow::new();
+        row.add_regular_button(button);
+        assert_eq!(serde_json::to_value(&row).unwrap()["components"][0]["style"], 4);
+
+        let err = RegularButton::new().style_from_str("blurple").map(|_| ()).unwrap_err();
+        assert!(contains_all_predicate(vec!["unknown button style", "blurple"])(&err.to_string()));
+    }
+
+    #[test]
+    fn action_row_from_json() {
+        let row = ActionRow::from_json(
+            r#"{"type": 1, "components": [
+                {"type": 2, "style": 1, "label": "Yes", "custom_id": "yes"},
+                {"type": 2, "style": 5, "label": "Docs", "url": "https://example.com"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut message = Message::new();
+        message.add_action_row(row);
+        assert!(message.check_compatibility(&mut MessageContext::new()).is_ok());
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["components"][0]["components"][1]["style"], 5);
+
+        let err = ActionRow::from_json(r#"{"type": 1, "components": [{"type": 9, "custom_id": "x"}]}"#).unwrap_err();
+        assert!(contains_all_predicate(vec!["unknown component type", "9"])(&err.to_string()));
+
+        let err = message
+            .components_from_json(r#"[{"type": 1, "components": []}]"#)
+            .unwrap_err();
+        assert!(contains_all_predicate(vec!["action row", "empty"])(&err.to_string()));
+        assert_eq!(message.action_row_count(), 1);
+    }
+
+    #[test]
+    fn message_diff() {
+        let build = |content: Option<&str>| {
+            let mut message = Message::new();
+            if let Some(content) = content {
+                message.content(content);
+            }
+            message
+                .embed(|embed| embed.title("status"))
+                .action_row(|row| row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("0")));
+            message
+        };
+
+        let diff = build(Some("old")).diff(&build(Some("new")));
+        assert_eq!(diff.content.as_deref(), Some("new"));
+        assert!(diff.embeds.is_none());
+        assert!(diff.allow_mentions.is_none());
+        assert!(diff.action_rows.is_none());
+        assert_eq!(serde_json::to_value(&diff).unwrap(), serde_json::json!({"content": "new"}));
+
+        assert_eq!(build(Some("old")).diff(&build(None)).content.as_deref(), Some(""));
+        assert!(build(None).diff(&build(None)).content.is_none());
+    }
+
+    #[test]
+    fn message_emojis_collected() {
+        let mut message = Message::new();
+        message
+            .action_row(|row| {
+                row.regular_button(|btn| {
+                    btn.style(NonLinkButtonStyle::Primary)
+                        .custom_id("0")
+                        .emoji("10", "wave", false)
+                })
+                .regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("1"))
+            })
+            .action_row(|row| {
+                row.select_menu(|menu| {
+                    menu.custom_id("menu")
+                        .option(|opt| opt.label("a").value("a").emoji("20", "party", true))
+                })
+            });
+
+        let emojis: Vec<&str> = message.emojis().iter().map(|emoji| emoji.id.as_str()).collect();
+        assert_eq!(emojis, vec!["10", "20"]);
+        assert!(Message::new().emojis().is_empty());
+    }
+
+    #[test]
+    fn ephemeral_flag_depends_on_target() {
+        let mut message = Message::new();
+        message.content("only you").flag(Message::FLAG_EPHEMERAL);
+
+        let err = message.check_for(ValidationTarget::Execute).unwrap_err();
+        assert!(contains_all_predicate(vec!["ephemeral", "interaction followup"])(&err.to_string()));
+        assert!(message.check_compatibility(&mut MessageContext::new()).is_err());
+        assert!(message.check_for(ValidationTarget::InteractionFollowup).is_ok());
+    }
+
+    #[test]
+    fn loading_placeholder_message() {
+        let message = Message::loading("Fetching results");
+        assert!(message.check_compatibility(&mut MessageContext::new()).is_ok());
+        assert_eq!(message.content.as_deref(), Some("\u{23f3} Fetching results"));
+
+        let json = serde_json::to_value(&message).unwrap();
+        let components = json["components"][0]["components"].as_array().unwrap();
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0]["disabled"], true);
+    }
+
+    #[test]
+    fn validation_error_suggestions() {
+        let mut message = Message::new();
+        message.content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1));
+        let err = message.check_compatibility(&mut MessageContext::new()).unwrap_err();
+        assert!(contains_all_predicate(vec!["split", "multiple messages"])(&err.suggestion().unwrap()));
+        let report = err.report();
+        assert!(report.starts_with(&err.to_string()));
+        assert!(report.contains("\n  suggestion: Split"));
+
+        let err = ValidationError::Other("Empty action row detected!".to_string());
+        assert_eq!(err.suggestion(), None);
+        assert_eq!(err.report(), "Empty action row detected!");
+    }
+
+    #[test]
+    fn embed_and_component_violations_reported_together() {
+        let mut message = Message::new();
+        message
+            .embed(|embed| embed.description(&"a".repeat(4000)))
+            .embed(|embed| embed.description(&"b".repeat(4000)))
+            .action_row(|row| row);
+
+        let violations = message.check_all();
+        assert_eq!(violations.len(), 2, "Unexpected violations {:?}", violations);
+        assert!(contains_all_predicate(vec!["across all embeds", "interval"])(&violations[0].to_string()));
+        assert!(contains_all_predicate(vec!["action row", "empty"])(&violations[1].to_string()));
+    }
+
+    #[test]
+    fn relaxed_limits_allow_more_fields() {
+        fn build(message: &mut Message) -> &mut Message {
+            message.embed(|embed| {
+                for index in 0..30 {
+                    embed.field(&index.to_string(), "value", false);
+                }
+                embed
+            })
+        }
+        let client = WebhookClient::new("https://discord.com/api/webhooks/1234/token");
+        assert!(client.build_message(build).is_err());
+
+        let relaxed = Limits {
+            embed_field_count: Interval::from_min_max(0, 50),
+            ..Limits::default()
+        };
+        let client = client.with_limits(relaxed);
+        assert_eq!(client.build_message(build).unwrap().embeds[0].fields.len(), 30);
+    }
+
+    #[test]
+    fn custom_limits_cover_embeds_and_rows() {
+        let strict = Limits {
+            embed_field_value_len: Interval::from_min_max(0, 5),
+            action_row_button_count: Interval::from_min_max(0, 1),
+            ..Limits::default()
+        };
+        let mut message = Message::new();
+        message.embed(|embed| embed.field("name", "too long", false));
+        assert!(message.check_compatibility(&mut MessageContext::new()).is_ok());
+        let err = message.check_with_limits(strict).unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "field value"])(&err.to_string()));
+
+        let mut message = Message::new();
+        message.action_row(|row| {
+            row.regular_button(|button| button.style(NonLinkButtonStyle::Primary).custom_id("a"))
+                .regular_button(|button| button.style(NonLinkButtonStyle::Primary).custom_id("b"))
+        });
+        assert!(message.check_with_limits(Limits::default()).is_ok());
+        let err = message.check_with_limits(strict).unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "button count"])(&err.to_string()));
+
+        let relaxed = Limits {
+            embed_field_value_len: Interval::from_min_max(0, 10),
+            ..Limits::default()
+        };
+        assert_eq!(
+            strict.intersection(&relaxed).unwrap().embed_field_value_len,
+            Interval::from_min_max(0, 5)
+        );
+    }
+
+    #[test]
+    fn allowed_mentions_parse_values() {
+        let mentions = AllowedMentions::new(Some(vec![AllowedMention::EveryoneMention]), None, None, false);
+        assert_eq!(mentions.parse_values(), ["everyone"]);
+        assert!(AllowedMentions::none().parse_values().is_empty());
+    }
+
+    #[test]
+    fn allowed_mentions_parse_none_preserved() {
+        let mentions = AllowedMentions::new(None, Some(vec!["1".to_string()]), None, false);
+        assert_eq!(mentions.parse, None);
+        assert_eq!(AllowedMentions::none().parse, Some(vec![]));
+    }
+
+    #[test]
+    fn allowed_mentions_unset_parse_omitted() {
+        let mut message = Message::new();
+        message.content("<@&1>").allow_mentions(None, Some(vec!["1".to_string()]), None, false);
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["allowed_mentions"], serde_json::json!({"roles": ["1"], "replied_user": false}));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+        #[test]
+        fn random_input_never_panics(
+            strings in prop::collection::vec(
+                prop::collection::vec(prop::sample::select(vec!['a', ':', '\n', '*', '`', 'é', '⏳', '\0']), 0..=3000)
+                    .prop_map(|chars| chars.into_iter().collect::<String>()),
+                12,
+            ),
+            counts in prop::collection::vec(0u8..40, 4),
+        ) {
+            let mut message = Message::new();
+            message
+                .content(&strings[0])
+                .username(&strings[1])
+                .embed(|embed| {
+                    for index in 0..counts[0] {
+                        embed.field(&strings[index as usize % 12], &strings[2], false);
+                    }
+                    let _ = embed.field_chunked(&strings[3], &strings[4], true);
+                    let _ = embed.try_footer(&strings[5], None);
+                    embed.title(&strings[6]).description(&strings[7])
+                })
+                .action_row(|row| {
+                    row.regular_button(|button| {
+                        button.custom_id(&strings[8]).label(&strings[9]).style(NonLinkButtonStyle::Primary)
+                    })
+                    .select_menu(|menu| {
+                        menu.custom_id(&strings[10])
+                            .min_values(counts[1])
+                            .max_values(counts[2])
+                            .option(|option| option.label(&strings[11]).value(&strings[0]))
+                    })
+                });
+
+            let _ = message.check_all();
+            let _ = message.preflight();
+            let _ = message.to_plaintext();
+            let _ = message.warnings();
+            let _ = CustomId::parse(&strings[8]);
+            let _ = ActionRow::from_json(&strings[9]);
+            let _ = message.remove_action_row(counts[3] as usize);
+        }
+    }
+
+    #[test]
+    fn validated_json() {
+        let mut message = Message::new();
+        message.content("snapshot");
+        let json: serde_json::Value = serde_json::from_str(&message.to_validated_json().unwrap()).unwrap();
+        assert_eq!(json["content"], "snapshot");
+
+        message.content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1));
+        let err = message.to_validated_json().unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "content", "length"])(&err.to_string()));
+    }
+
+    #[test]
+    fn components_disabled_by_predicate() {
+        let mut message = Message::new();
+        message
+            .action_row(|row| {
+                row.regular_button(|button| button.style(NonLinkButtonStyle::Success).custom_id("yes"))
+                    .regular_button(|button| button.style(NonLinkButtonStyle::Danger).custom_id("no"))
+            })
+            .action_row(|row| row.select_menu(|menu| menu.custom_id("pick").option(|opt| opt.label("a").value("a"))))
+            .disable_components_where(|custom_id| custom_id == "yes");
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["components"][0]["components"][0]["disabled"], true);
+        assert!(json["components"][0]["components"][1]["disabled"].is_null());
+        assert!(json["components"][1]["components"][0]["disabled"].is_null());
+    }
+
+    #[test]
+    fn incoming_webhook_rejects_components() {
+        let mut message = Message::for_incoming_webhook();
+        message.content("hi").embed(|embed| embed.title("title"));
+        assert!(message.check_all().is_empty());
+        assert_eq!(message.webhook_kind(), Some(WebhookKind::Incoming));
+
+        // components can be added only after converting into a `Message`
+        let mut message = message.into_message();
+        message.action_row(|row| row.regular_button(|button| button.style(NonLinkButtonStyle::Primary).custom_id("a")));
+        let err = message.check_compatibility(&mut MessageContext::new()).unwrap_err();
+        assert!(contains_all_predicate(vec!["incoming webhook", "components"])(&err.to_string()));
+
+        let mut message = Message::for_application_webhook();
+        message.action_row(|row| row.regular_button(|button| button.style(NonLinkButtonStyle::Primary).custom_id("a")));
+        assert!(message.check_all().is_empty());
+    }
+
+    #[test]
+    fn confirm_cancel_row_ids_validated() {
+        let err = ActionRow::confirm_cancel("same", "same").unwrap_err();
+        assert_eq!(err, ValidationError::DuplicateCustomId("same".to_string()));
+        let err = ActionRow::confirm_cancel("", "no").unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "custom id"])(&err.to_string()));
+    }
+
+    #[test]
+    fn check_all_reports_every_violation() {
+        let mut message = Message::new();
+        message
+            .embed(|embed| embed.title(&"a".repeat(Embed::TITLE_LEN_INTERVAL.max_allowed + 1)))
+            .action_row(|row| row)
+            .action_row(|row| {
+                row.regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("0"))
+                    .regular_button(|btn| btn.style(NonLinkButtonStyle::Primary).custom_id("0"))
+            });
+
+        let violations = message.check_all();
+        assert_eq!(violations.len(), 3, "Unexpected violations {:?}", violations);
+        assert!(contains_all_predicate(vec!["embed title"])(&violations[0].to_string()));
+        assert!(contains_all_predicate(vec!["action row", "empty"])(&violations[1].to_string()));
+        assert_eq!(violations[2], ValidationError::DuplicateCustomId("0".to_string()));
+
+        // the short-circuiting check reports only the first violation
+        let err = message.check_compatibility(&mut MessageContext::new()).unwrap_err();
+        assert_eq!(err, violations[0]);
+    }
+
+    #[test]
+    fn check_all_valid_message() {
+        let mut message = Message::new();
+        message.content("valid").embed(|embed| embed.title("valid"));
+        assert!(message.check_all().is_empty());
+    }
+
+    /// A reader counting the bytes read from it.
+    struct CountingReader {
+        inner: std::io::Cursor<Vec<u8>>,
+        read: Arc<AtomicUsize>,
+    }
+
+    impl AsyncRead for CountingReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let before = buf.filled().len();
+            let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+            self.read.fetch_add(buf.filled().len() - before, Ordering::SeqCst);
+            result
+        }
+    }
+
+    #[tokio::test]
+    async fn attachment_streamed_without_buffering() {
+        const SIZE: usize = 1024 * 1024;
+        let read = Arc::new(AtomicUsize::new(0));
+        let reader = CountingReader {
+            inner: std::io::Cursor::new(vec![b'x'; SIZE]),
+            read: read.clone(),
+        };
+        let mut message = Message::new();
+        message.attachment_stream("big.txt", reader, SIZE);
+
+        let body = multipart_body("boundary", "{}", &message.attachments).unwrap();
+        assert_eq!(read.load(Ordering::SeqCst), 0, "The attachment was read before sending");
+
+        let body = hyper::body::to_bytes(body).await.unwrap();
+        assert_eq!(read.load(Ordering::SeqCst), SIZE);
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(&format!("\r\n\r\n{}\r\n--boundary--", "x".repeat(SIZE))));
+
+        // the reader has been consumed
+        assert!(multipart_body("boundary", "{}", &message.attachments).is_err());
+    }
+
+    #[tokio::test]
+    async fn streamed_attachment_sent() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        message.attachment_stream("a.txt", std::io::Cursor::new(b"streamed".to_vec()), 8);
+        client.send_message(&message).await.unwrap();
+
+        let body = String::from_utf8(server.requests()[0].body.clone()).unwrap();
+        assert!(body.contains(r#"name="files[0]"; filename="a.txt""#));
+        assert!(body.contains("streamed"));
+    }
+
+    #[test]
+    fn content_len_enforced() {
+        assert_message_error(
+            |message| message.content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1)),
+            contains_all_predicate(vec!["interval", "content length"]),
+        );
+    }
+
+    #[tokio::test]
+    async fn send_message_validates_before_sending() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        message.content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1));
+
+        let err = client.send_message(&message).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "content length"])(&err.to_string()));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_unchecked_skips_validation() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        message.action_row(|row| row);
+        assert!(message.check_compatibility(&mut MessageContext::new()).is_err());
+
+        assert!(client.send_unchecked(&message).await.unwrap());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn non_webhook_url_rejected() {
+        let client = WebhookClient::new("https://discord.com");
+        let err = client.send(|message| message.content("lost")).await.unwrap_err();
+        assert!(
+            contains_all_predicate(vec!["https://discord.com", "not a webhook url"])(&err.to_string()),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn url_check_skippable() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let url = server.url.replace("/api/webhooks/1234/token", "/custom");
+        let client = WebhookClient::new(&url).without_url_check();
+        assert!(client.send(|message| message.content("custom")).await.unwrap());
+        assert_eq!(server.requests()[0].uri.path(), "/custom");
+    }
+
+    #[test]
+    fn info_url() {
+        let url = "https://discord.com/api/webhooks/123/secret";
+        let client = WebhookClient::new(url);
+        assert_eq!(client.info_url(), url);
+        assert_eq!(client.info_url_redacted(), "https://discord.com/api/webhooks/123/***");
+    }
+
+    #[test]
+    fn token_masked() {
+        let token = Token::new("abcdefgh1234");
+        assert_eq!(format!("{:?}", token), "Token(***1234)");
+        assert_eq!(token.to_string(), "***1234");
+        assert_eq!(token.expose(), "abcdefgh1234");
+        assert_eq!(Token::new("abc").to_string(), "***");
+
+        let webhook: Webhook = serde_json::from_str(
+            r#"{"id": "1", "type": 1, "guild_id": "2", "channel_id": "3", "token": "secret-token"}"#,
+        )
+        .unwrap();
+        assert_eq!(webhook.token.expose(), "secret-token");
+        assert!(!format!("{:?}", webhook).contains("secret"));
+
+        let client = WebhookClient::new("https://discord.com/api/webhooks/1/secret-token");
+        assert!(!format!("{:?}", client).contains("secret"));
+    }
+
+    #[test]
+    fn follower_webhook_source_deserialized() {
+        let webhook: Webhook = serde_json::from_str(
+            r#"{"id": "1", "type": 2, "guild_id": "2", "channel_id": "3", "name": "news",
+                "source_guild": {"id": "4", "name": "Upstream", "icon": null},
+                "source_channel": {"id": "5", "name": "announcements"}}"#,
+        )
+        .unwrap();
+        assert_eq!(webhook.webhook_type, 2);
+        assert_eq!(webhook.token.expose(), "");
+        let source_guild = webhook.source_guild.unwrap();
+        assert_eq!((source_guild.id.as_str(), source_guild.name.as_deref()), ("4", Some("Upstream")));
+        assert_eq!(webhook.source_channel.unwrap().name.as_deref(), Some("announcements"));
+
+        let webhook: Webhook = serde_json::from_str(
+            r#"{"id": "1", "type": 1, "guild_id": "2", "channel_id": "3", "token": "t"}"#,
+        )
+        .unwrap();
+        assert!(webhook.source_guild.is_none() && webhook.source_channel.is_none());
+    }
+
+    #[test]
+    fn deprecated_host_normalized() {
+        let client = WebhookClient::new("https://discordapp.com/api/webhooks/1/token?wait=true");
+        assert!(client.uses_deprecated_host());
+        let client = client.with_host_normalization(true);
+        assert!(!client.uses_deprecated_host());
+        assert_eq!(client.info_url(), "https://discord.com/api/webhooks/1/token?wait=true");
+
+        let client = WebhookClient::new("https://canary.discordapp.com/api/webhooks/1/token");
+        assert_eq!(
+            client.with_host_normalization(true).info_url(),
+            "https://canary.discord.com/api/webhooks/1/token"
+        );
+        let client = WebhookClient::new("https://discordapp.com/api/webhooks/1/token").with_host_normalization(false);
+        assert!(client.uses_deprecated_host());
+        assert!(!WebhookClient::new("https://notdiscordapp.com/api/webhooks/1/token").uses_deprecated_host());
+    }
+
+    #[test]
+    fn url_token_redacted() {
+        assert_eq!(
+            redact_url("https://discord.com/api/webhooks/123/secret?wait=true"),
+            "https://discord.com/api/webhooks/123/***?wait=true"
+        );
+        assert_eq!(
+            redact_url("https://discord.com/api/webhooks/123/secret"),
+            "https://discord.com/api/webhooks/123/***"
+        );
+        assert_eq!(redact_url("https://discord.com"), "https://discord.com");
+    }
+
+    /// A subscriber recording the fields of all events as `name=value` strings.
+    #[cfg(feature = "tracing")]
+    struct CapturingSubscriber {
+        events: Arc<std::sync::Mutex<Vec<String>>>,
+        spans: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[cfg(feature = "tracing")]
+    struct FieldRecorder<'a>(&'a mut Vec<String>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for FieldRecorder<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for CapturingSubscriber {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut fields = vec![];
+            span.record(&mut FieldRecorder(&mut fields));
+            spans.push(format!("{} {}", span.metadata().name(), fields.join(" ")));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = vec![];
+            event.record(&mut FieldRecorder(&mut fields));
+            self.events.lock().unwrap().push(fields.join(" "));
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn send_emits_status_event() {
+        let events = Arc::new(std::sync::Mutex::new(vec![]));
+        let spans = Arc::new(std::sync::Mutex::new(vec![]));
+        let _guard = tracing::subscriber::set_default(CapturingSubscriber {
+            events: events.clone(),
+            spans: spans.clone(),
+        });
+
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        client.send(|message| message.content("traced")).await.unwrap();
+
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|event| event.contains("status=204")), "{:?}", events);
+        let spans = spans.lock().unwrap();
+        let span = spans.iter().find(|span| span.starts_with("webhook.execute")).unwrap();
+        assert!(span.contains("/api/webhooks/1234/***"), "{}", span);
+        assert!(!span.contains("token"), "{}", span);
+    }
+
+    #[tokio::test]
+    async fn max_concurrency_respected() {
+        let server = MockServer::start_with_delay(Duration::from_millis(50), |_, _| {
+            response(StatusCode::NO_CONTENT, "")
+        });
+        let client = WebhookClient::new(&server.url).with_max_concurrency(2);
+
+        let sends: Vec<_> = (0..8)
+            .map(|i| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    client.send(|message| message.content(&i.to_string())).await
+                })
+            })
+            .collect();
+        for send in sends {
+            assert!(send.await.unwrap().unwrap());
+        }
+
+        assert_eq!(server.requests().len(), 8);
+        assert_eq!(server.max_in_flight(), 2);
+    }
+
+    #[tokio::test]
+    async fn zero_max_concurrency_unlimited() {
+        let server = MockServer::start_with_delay(Duration::from_millis(50), |_, _| {
+            response(StatusCode::NO_CONTENT, "")
+        });
+        let client = WebhookClient::new(&server.url).with_max_concurrency(2).with_max_concurrency(0);
+
+        let sends = (0..4).map(|i| {
+            let client = client.clone();
+            async move { client.send(|message| message.content(&i.to_string())).await }
+        });
+        for result in futures_util::future::join_all(sends).await {
+            assert!(result.unwrap());
+        }
+
+        assert_eq!(server.max_in_flight(), 4);
+    }
+
+    #[tokio::test]
+    async fn send_to_all_clients() {
+        let first = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let second = MockServer::with_status(StatusCode::BAD_REQUEST, "nope");
+        let clients = [WebhookClient::new(&first.url), WebhookClient::new(&second.url)];
+
+        let results = WebhookClient::send_to_all(&clients, |message| message.content("all"))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(*results[0].as_ref().unwrap());
+        assert!(results[1].is_err());
+        assert_eq!(first.requests()[0].body_json()["content"], "all");
+        assert_eq!(second.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_to_all_no_clients() {
+        let results = WebhookClient::send_to_all(&[], |message| message.content("nobody"))
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_to_all_invalid_message() {
+        let first = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let second = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let clients = [WebhookClient::new(&first.url), WebhookClient::new(&second.url)];
+
+        let err = WebhookClient::send_to_all(&clients, |message| message.action_row(|row| row))
+            .await
+            .unwrap_err();
+        assert!(contains_all_predicate(vec!["action row", "empty"])(&err.to_string()));
+        assert!(first.requests().is_empty());
+        assert!(second.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_to_all_respects_limits_of_each_client() {
+        let first = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let second = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let strict = Limits {
+            content_len: Interval::from_min_max(0, 5),
+            ..Limits::default()
+        };
+        let clients = [WebhookClient::new(&first.url), WebhookClient::new(&second.url).with_limits(strict)];
+
+        let err = WebhookClient::send_to_all(&clients, |message| message.content("too long"))
+            .await
+            .unwrap_err();
+        assert!(contains_all_predicate(vec!["interval", "content length"])(&err.to_string()));
+        assert!(first.requests().is_empty());
+        assert!(second.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn thread_id_from_message() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        message.content("in thread").thread_id("777");
+        assert!(client.send_message(&message).await.unwrap());
+
+        let request = &server.requests()[0];
+        assert_eq!(request.uri.query(), Some("thread_id=777"));
+        assert!(request.body_json().get("thread_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn send_to_thread_overrides_message_thread() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&format!("{}?wait=false", server.url));
+        client
+            .send_to_thread("2", |message| message.content("in thread").thread_id("1"))
+            .await
+            .unwrap();
+        assert_eq!(server.requests()[0].uri.query(), Some("wait=false&thread_id=2"));
+    }
+
     #[test]
-    fn max_button_count_enforced_only_per_action_row() {
-        assert_valid_message(|message| {
-            for i in 0..Message::ACTION_ROW_COUNT_INTERVAL.max_allowed {
-                message.action_row(|row| {
-                    for j in 0..(ActionRow::BUTTON_COUNT_INTERVAL.max_allowed) {
-                        row.regular_button(|btn| {
-                            btn.style(NonLinkButtonStyle::Primary)
-                                .custom_id(&(i.to_string() + &j.to_string()))
-                        });
-                    }
-                    row
-                });
-            }
-            message
+    fn bot_request_construction() {
+        let client = WebhookClient::new("https://discord.com/api/v10/webhooks/1/token");
+        let request = client.bot_request("channels/55", "bot-secret").unwrap();
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.uri(), "https://discord.com/api/v10/channels/55");
+        assert_eq!(request.headers()["authorization"], "Bot bot-secret");
+    }
+
+    #[tokio::test]
+    async fn resolve_names() {
+        let server = MockServer::start(|_, request| match request.uri.path() {
+            "/api/channels/55" => response(StatusCode::OK, r#"{"id": "55", "name": "general"}"#),
+            "/api/guilds/66" => response(StatusCode::OK, r#"{"id": "66", "name": "Guild"}"#),
+            _ => response(
+                StatusCode::OK,
+                r#"{"id": "1234", "type": 1, "guild_id": "66", "channel_id": "55", "token": "token"}"#,
+            ),
         });
+        let client = WebhookClient::new(&server.url);
+
+        let info = client.resolve_names("bot-secret").await.unwrap();
+        assert_eq!(info.webhook.id, "1234");
+        assert_eq!(info.channel_name.as_deref(), Some("general"));
+        assert_eq!(info.guild_name.as_deref(), Some("Guild"));
+
+        let requests = server.requests();
+        assert!(requests[0].headers.get("authorization").is_none());
+        assert_eq!(requests[1].headers["authorization"], "Bot bot-secret");
     }
 
-    #[test]
-    fn message_valid_basic() {
-        assert_valid_message(|message| {
-            message
-                .content("@test")
-                .username("test")
-                .avatar_url("test")
-                .embed(|embed| {
-                    embed
-                        .title("test")
-                        .description("test")
-                        .footer("test", Some(String::from("test")))
-                        .image("test")
-                        .thumbnail("test")
-                        .author(
-                            "test",
-                            Some(String::from("test")),
-                            Some(String::from("test")),
-                        )
-                        .field("test", "test", false)
-                })
+    #[tokio::test]
+    async fn redirects_followed_when_enabled() {
+        let server = MockServer::start(|_, request| match request.uri.path() {
+            "/api/webhooks/1234/token" => hyper::Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header("location", "/api/webhooks/1234/moved")
+                .body(hyper::Body::empty())
+                .unwrap(),
+            _ => response(
+                StatusCode::OK,
+                r#"{"id": "1234", "type": 1, "guild_id": "66", "channel_id": "55", "token": "token"}"#,
+            ),
         });
+
+        assert!(WebhookClient::new(&server.url).get_information().await.is_err());
+        assert_eq!(server.requests().len(), 1);
+
+        let client = WebhookClient::new(&server.url).with_redirect_policy(true, 2);
+        let webhook = client.get_information().await.unwrap();
+        assert_eq!(webhook.id, "1234");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].uri.path(), "/api/webhooks/1234/moved");
+        assert_eq!(requests[2].method, Method::GET);
+
+        // a 301 would continue without the body, so the send is not redirected
+        let mut message = Message::new();
+        message.content("redirected");
+        let err = client.send_message(&message).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["redirect", "post", "drop the request body"])(&err.to_string()));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[3].method, Method::POST);
     }
 
-    #[test]
-    fn embed_title_len_enforced() {
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .title(&"a".repeat(Embed::TITLE_LEN_INTERVAL.max_allowed + 1))
-                })
-        },
-     contains_all_predicate(vec!["interval", "embed", "title", "length"]),
-        )
+    #[tokio::test]
+    async fn redirects_to_other_hosts_refused() {
+        let server = MockServer::start(|_, _| {
+            hyper::Response::builder()
+                .status(StatusCode::TEMPORARY_REDIRECT)
+                .header("location", "http://other.invalid/api/webhooks/1234/token")
+                .body(hyper::Body::empty())
+                .unwrap()
+        });
+        let client = WebhookClient::new(&server.url).with_redirect_policy(true, 2);
+
+        let err = client.send(|message| message.content("hi")).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["different host", "other.invalid"])(&err.to_string()));
+        assert_eq!(server.requests().len(), 1);
     }
 
-    #[test]
-    fn embed_description_len_enforced() {
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .description(&"a".repeat(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed + 1))
-                })
-        },
-     contains_all_predicate(vec!["interval", "embed", "description", "length"]),
-        )
+    #[tokio::test]
+    async fn applied_tags_checked_against_forum() {
+        let server = MockServer::start(|_, request| match request.uri.path() {
+            "/api/channels/55" => response(
+                StatusCode::OK,
+                r#"{"id": "55", "available_tags": [{"id": "1", "name": "bug"}, {"id": "2", "name": "idea"}]}"#,
+            ),
+            _ => response(
+                StatusCode::OK,
+                r#"{"id": "1234", "type": 1, "guild_id": "66", "channel_id": "55", "token": "token"}"#,
+            ),
+        });
+        let client = WebhookClient::new(&server.url);
+
+        let mut message = Message::new();
+        client.check_applied_tags(&message, "bot-secret").await.unwrap();
+        assert!(server.requests().is_empty());
+
+        message.applied_tag("2");
+        client.check_applied_tags(&message, "bot-secret").await.unwrap();
+        message.applied_tag("3");
+        let err = client.check_applied_tags(&message, "bot-secret").await.unwrap_err();
+        assert!(contains_all_predicate(vec!["applied tag", "(3)"])(&err.to_string()));
+        assert_eq!(server.requests()[1].headers["authorization"], "Bot bot-secret");
     }
 
-    #[test]
-    fn embed_author_name_len_enforced() {
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .author(&"a".repeat(EmbedAuthor::NAME_LEN_INTERVAL.max_allowed + 1), None, None)
-                })
-        },
-         contains_all_predicate(vec!["interval", "embed", "author", "name", "length"]),
-        )
+    #[tokio::test]
+    async fn connect_verifies_webhook() {
+        let server = MockServer::with_status(
+            StatusCode::OK,
+            r#"{"id": "1234", "type": 1, "guild_id": "66", "channel_id": "55", "token": "token"}"#,
+        );
+        let client = WebhookClient::connect(&server.url).await.unwrap();
+        assert_eq!(client.information().unwrap().channel_id, "55");
+        assert_eq!(client.clone().information().unwrap().id, "1234");
+        assert!(WebhookClient::new(&server.url).information().is_none());
+
+        let server = MockServer::with_status(StatusCode::NOT_FOUND, r#"{"message": "Unknown Webhook", "code": 10015}"#);
+        let err = WebhookClient::connect(&server.url).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["unknown webhook"])(&err.to_string()));
     }
 
-    #[test]
-    fn embed_footer_text_len_enforced() {
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .footer(&"a".repeat(EmbedFooter::TEXT_LEN_INTERVAL.max_allowed + 1), None)
-                })
-        },
-         contains_all_predicate(vec!["interval", "embed", "footer", "text", "length"]),
-        )
+    #[tokio::test]
+    async fn send_with_request_id_header() {
+        let server = MockServer::start(|index, _| match index {
+            0 => response(StatusCode::NO_CONTENT, ""),
+            _ => response(StatusCode::BAD_REQUEST, r#"{"message": "Invalid Form Body"}"#),
+        });
+        let client = WebhookClient::new(&server.url);
+
+        client.send_with_request_id(|message| message.content("a"), "req-1").await.unwrap();
+        let err = client
+            .send_with_request_id(|message| message.content("b"), "req-2")
+            .await
+            .unwrap_err();
+        assert!(contains_all_predicate(vec!["req-2", "invalid form body"])(&err.to_string()));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].headers["x-request-id"], "req-1");
+        assert_eq!(requests[1].headers["x-request-id"], "req-2");
+        client.send(|message| message.content("c")).await.unwrap_err();
+        assert!(server.requests()[2].headers.get("x-request-id").is_none());
     }
 
-    #[test]
-    fn embed_field_name_len_enforced() {
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .field(&"a".repeat(EmbedField::NAME_LEN_INTERVAL.max_allowed + 1), "None", false)
-                })
-        },
-         contains_all_predicate(vec!["interval", "embed", "field", "name", "length"]),
-        )
+    #[tokio::test]
+    async fn control_chars_sanitized_when_enabled() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let mut message = Message::new();
+        message
+            .content("a\0b\nc")
+            .embed(|embed| embed.title("t\u{7}itle").description("d\tx"));
+
+        WebhookClient::new(&server.url).send_message(&message).await.unwrap();
+        WebhookClient::new(&server.url)
+            .with_sanitize_control_chars(true)
+            .send_message(&message)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].body_json()["content"], "a\0b\nc");
+        let body = requests[1].body_json();
+        assert_eq!(body["content"], "ab\nc");
+        assert_eq!(body["embeds"][0]["title"], "title");
+        assert_eq!(body["embeds"][0]["description"], "d\tx");
     }
 
-    #[test]
-    fn embed_field_value_len_enforced() {
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .field("None", &"a".repeat(EmbedField::VALUE_LEN_INTERVAL.max_allowed + 1), false)
-                })
-        },
-     contains_all_predicate(vec!["interval", "embed", "field", "value", "length"]),
-        )
+    #[tokio::test]
+    async fn send_sequence_rolls_back() {
+        let server = MockServer::start(|index, request| match (index, &request.method) {
+            (0, _) => response(StatusCode::OK, r#"{"id": "100", "channel_id": "55", "content": "first"}"#),
+            (_, &Method::DELETE) => response(StatusCode::NO_CONTENT, ""),
+            _ => response(StatusCode::BAD_REQUEST, r#"{"message": "Invalid Form Body"}"#),
+        });
+        let client = WebhookClient::new(&server.url);
+        let mut first = Message::new();
+        first.content("first");
+        let mut second = Message::new();
+        second.content("second");
+
+        let err = client.send_sequence(&[first, second]).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["invalid form body"])(&err.to_string()));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].uri.query(), Some("wait=true"));
+        assert_eq!(requests[2].method, Method::DELETE);
+        assert_eq!(requests[2].uri.path(), "/api/webhooks/1234/token/messages/100");
     }
 
-    #[test]
-    fn embed_total_char_length_enforced() {
-        // adds 2 embeds with maximum length descriptions
-        // which should overflow the maximum allowed characters for embeds in total
-        assert!(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed * 2 > Message::EMBED_TOTAL_TEXT_LEN_INTERVAL.max_allowed, "Key test values modified, fix this test!");
+    #[tokio::test]
+    async fn send_sequence_validates_all_first() {
+        let server = MockServer::with_status(StatusCode::OK, r#"{"id": "100", "channel_id": "55"}"#);
+        let client = WebhookClient::new(&server.url);
+        let mut valid = Message::new();
+        valid.content("valid");
+        let mut invalid = Message::new();
+        invalid.action_row(|row| row);
 
-        assert_message_error(|message| {
-            message
-                .embed(|embed| {
-                    embed
-                        .description(&"a".repeat(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed))
-                })
-                .embed(|embed| {
-                    embed
-                        .description(&"a".repeat(Embed::DESCRIPTION_LEN_INTERVAL.max_allowed))
-                })
-        },
-         contains_all_predicate(vec!["interval", "character", "count", "embed"]),
-        )
+        assert!(client.send_sequence(&[valid, invalid]).await.is_err());
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn edit_keeps_attachments_and_uploads() {
+        let mut edit = EditMessage::new();
+        edit.keep_attachment("1").attachment("new.txt", b"new".to_vec());
+        assert_eq!(
+            serde_json::to_value(&edit).unwrap(),
+            serde_json::json!({"attachments": [
+                {"id": "1"},
+                {"id": 0, "filename": "new.txt", "description": null}
+            ]})
+        );
+
+        let server = MockServer::with_status(StatusCode::OK, r#"{"id": "100", "channel_id": "55"}"#);
+        WebhookClient::new(&server.url).edit_message("100", &edit).await.unwrap();
+        let request = &server.requests()[0];
+        assert_eq!(request.method, Method::PATCH);
+        assert!(request.headers["content-type"].to_str().unwrap().starts_with("multipart/form-data"));
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains(r#"{"attachments":[{"id":"1"},"#));
+        assert!(body.contains(r#"name="files[0]"; filename="new.txt""#));
+    }
+
+    #[tokio::test]
+    async fn global_rate_limit_pauses_all_sends() {
+        let server = MockServer::start(|index, _| match index {
+            0 => hyper::Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header("x-ratelimit-global", "true")
+                .body(hyper::Body::from(r#"{"retry_after": 0.3, "global": true}"#))
+                .unwrap(),
+            _ => response(StatusCode::NO_CONTENT, ""),
+        });
+        let client = WebhookClient::new(&server.url);
+
+        assert!(client.send(|message| message.content("limited")).await.is_err());
+        let start = std::time::Instant::now();
+        let other = client.clone();
+        let (first, second) = tokio::join!(
+            client.send(|message| message.content("first")),
+            other.send(|message| message.content("second"))
+        );
+        assert!(first.unwrap() && second.unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn global_rate_limit_pauses_edits() {
+        let server = MockServer::start(|index, _| match index {
+            0 => hyper::Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header("x-ratelimit-global", "true")
+                .body(hyper::Body::from(r#"{"retry_after": 0.3, "global": true}"#))
+                .unwrap(),
+            _ => response(
+                StatusCode::OK,
+                r#"{"id": "1", "channel_id": "2", "content": "edited", "embeds": []}"#,
+            ),
+        });
+        let client = WebhookClient::new(&server.url);
+
+        assert!(client.send(|message| message.content("limited")).await.is_err());
+        let start = std::time::Instant::now();
+        client.edit("1", |edit| edit.content("edited")).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert_eq!(server.requests()[1].method, Method::PATCH);
     }
 
     #[test]
-    #[should_panic]
-    fn field_count_enforced() {
-        assert_valid_message(|message| {
-            message
-                .embed(|embed| {
-                    for _ in 0..Embed::FIELDS_LEN_INTERVAL.max_allowed + 1 {
-                        embed.field("None", "a", false);
-                    }
-                    embed
-                })
+    fn best_effort_send_outside_of_runtime() {
+        let client = WebhookClient::new("https://discord.com/api/webhooks/1/token");
+        let err = client.send_best_effort(|message| message.content("hi")).unwrap_err();
+        assert!(matches!(err, WebhookError::Other(_)));
+        assert!(contains_all_predicate(vec!["background", "runtime"])(&err.to_string()));
+    }
+
+    #[test]
+    fn poisoned_rate_limit_state_used() {
+        let client = WebhookClient::new("https://discord.com/api/webhooks/1/token");
+        let inner = client.inner.clone();
+        let panicked = std::thread::spawn(move || {
+            let _global = inner.global_rate_limit_until.lock().unwrap();
+            let _bucket = inner.bucket_reset_at.lock().unwrap();
+            panic!("poisoning the rate limit state");
         })
+        .join();
+        assert!(panicked.is_err());
+        assert!(client.inner.global_rate_limit_until.is_poisoned());
+        assert_eq!(client.next_available(), None);
+    }
+
+    #[tokio::test]
+    async fn next_available_after_exhausted_bucket() {
+        let server = MockServer::start(|index, _| match index {
+            0 => hyper::Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header("x-ratelimit-remaining", "0")
+                .header("x-ratelimit-reset-after", "2.5")
+                .body(hyper::Body::empty())
+                .unwrap(),
+            _ => response(StatusCode::NO_CONTENT, ""),
+        });
+        let client = WebhookClient::new(&server.url);
+        assert_eq!(client.next_available(), None);
+
+        client.send(|message| message.content("last one")).await.unwrap();
+        let next_available = client.next_available().unwrap();
+        assert!(next_available > tokio::time::Instant::now() + Duration::from_secs(2));
+        assert_eq!(client.clone().next_available(), Some(next_available));
+
+        client.send(|message| message.content("reset")).await.unwrap();
+        assert_eq!(client.next_available(), None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_send_retried() {
+        fn rate_limited_once(index: usize, _: &RecordedRequest) -> hyper::Response<hyper::Body> {
+            match index {
+                0 => hyper::Response::builder()
+                    .status(StatusCode::TOO_MANY_REQUESTS)
+                    .header("retry-after", "0.1")
+                    .body(hyper::Body::empty())
+                    .unwrap(),
+                _ => response(StatusCode::NO_CONTENT, ""),
+            }
+        }
+
+        let server = MockServer::start(rate_limited_once);
+        let client = WebhookClient::new(&server.url).with_max_retries(2);
+        let start = std::time::Instant::now();
+        assert!(client.send(|message| message.content("retried")).await.unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(server.requests().len(), 2);
+
+        let server = MockServer::start(rate_limited_once);
+        let client = WebhookClient::new(&server.url);
+        let err = client.send(|message| message.content("not retried")).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalid_message_never_sent() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+
+        let mut invalid = Message::new();
+        invalid.content(&"a".repeat(100_000)).action_row(|row| row);
+        let err = client.send_message(&invalid).await.unwrap_err();
+        assert!(matches!(err, WebhookError::Validation(_)));
+        let err = client.send(|message| message.action_row(|row| row)).await.unwrap_err();
+        assert!(matches!(err, WebhookError::Validation(_)));
+        assert!(server.requests().is_empty());
+
+        client.send(|message| message.content("valid")).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_best_effort_in_background() {
+        let server = MockServer::start_with_delay(Duration::from_millis(200), |_, _| {
+            response(StatusCode::INTERNAL_SERVER_ERROR, "")
+        });
+        let client = WebhookClient::new(&server.url);
+
+        assert!(client.send_best_effort(|message| message.action_row(|row| row)).is_err());
+        let start = std::time::Instant::now();
+        let handle = client.send_best_effort(|message| message.content("telemetry")).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+        handle.await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn edit_and_delete_in_thread() {
+        let server = MockServer::with_status(StatusCode::OK, r#"{"id": "100", "channel_id": "55"}"#);
+        let client = WebhookClient::new(&server.url);
+        let mut edit = EditMessage::new();
+        edit.content("edited").thread_id("777");
+        client.edit_message("100", &edit).await.unwrap();
+        client.delete_message("100", Some("777")).await.unwrap();
+        client.delete_message("100", None).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, Method::PATCH);
+        assert_eq!(requests[0].uri.path(), "/api/webhooks/1234/token/messages/100");
+        assert_eq!(requests[0].uri.query(), Some("thread_id=777"));
+        assert!(requests[0].body_json().get("thread_id").is_none());
+        assert_eq!(requests[1].method, Method::DELETE);
+        assert_eq!(requests[1].uri.query(), Some("thread_id=777"));
+        assert_eq!(requests[2].uri.query(), None);
+    }
+
+    #[tokio::test]
+    async fn send_message_wait_returns_created_message() {
+        let server = MockServer::with_status(
+            StatusCode::OK,
+            r#"{"id": "100", "channel_id": "55", "content": "hi", "timestamp": "2024-01-01T00:00:00+00:00"}"#,
+        );
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        message.content("hi");
+
+        let sent = client.send_message_wait(&message).await.unwrap();
+        assert_eq!(sent.id, "100");
+        assert_eq!(sent.channel_id, "55");
+        assert_eq!(sent.timestamp.as_deref(), Some("2024-01-01T00:00:00+00:00"));
+        assert_eq!(server.requests()[0].uri.query(), Some("wait=true"));
+
+        let server = MockServer::with_status(StatusCode::BAD_REQUEST, r#"{"message": "Invalid Form Body", "code": 50035}"#);
+        let client = WebhookClient::new(&server.url);
+        assert!(client.send_message_wait(&message).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn edit_with_builder() {
+        let server = MockServer::with_status(StatusCode::OK, r#"{"id": "100", "channel_id": "55", "content": "edited"}"#);
+        let client = WebhookClient::new(&server.url);
+        let edited = client.edit("100", |edit| edit.content("edited")).await.unwrap();
+        assert_eq!(edited.content, "edited");
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, Method::PATCH);
+        assert_eq!(request.uri.path(), "/api/webhooks/1234/token/messages/100");
+        assert_eq!(request.body_json(), serde_json::json!({"content": "edited"}));
+
+        let invalid = client.edit("100", |edit| edit.content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1)));
+        assert!(invalid.await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn edit_unknown_message_readable_error() {
+        let server = MockServer::with_status(StatusCode::NOT_FOUND, r#"{"message": "Unknown Message", "code": 10008}"#);
+        let client = WebhookClient::new(&server.url);
+        let err = client.edit("404", |edit| edit.content("edited")).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["message (404)", "not found"])(&err.to_string()));
+        let err = client.delete_message("404", None).await.unwrap_err();
+        assert!(contains_all_predicate(vec!["message (404)", "not found"])(&err.to_string()));
+    }
+
+    #[tokio::test]
+    async fn build_request_without_sending() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        let mut message = Message::new();
+        message.content("dry run");
+
+        let request = client.build_request(&message).await.unwrap();
+        assert_eq!(request.method(), Method::POST);
+        assert_eq!(request.uri().to_string(), server.url);
+        assert_eq!(request.headers()["content-type"], "application/json");
+        let body: serde_json::Value = serde_json::from_slice(request.body()).unwrap();
+        assert_eq!(body["content"], "dry run");
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn json_content_type_overridden() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url).with_json_content_type("application/json; charset=utf-8");
+        let mut message = Message::new();
+        message.content("proxied");
+
+        let request = client.build_request(&message).await.unwrap();
+        assert_eq!(request.headers()["content-type"], "application/json; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn pool_settings_applied() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url);
+        for _ in 0..3 {
+            client.send(|message| message.content("pooled")).await.unwrap();
+        }
+        assert_eq!(server.connections(), 1);
+
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url).with_pool_settings(0, Duration::from_secs(1));
+        for _ in 0..3 {
+            client.send(|message| message.content("not pooled")).await.unwrap();
+        }
+        assert_eq!(server.connections(), 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a network dropping the packets to non-routable addresses"]
+    async fn connect_timeout_fails_fast() {
+        let client = WebhookClient::new("http://10.255.255.1/api/webhooks/1234/token")
+            .with_connect_timeout(Duration::from_millis(200));
+        let start = std::time::Instant::now();
+        let err = client.send(|message| message.content("unreachable")).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(contains_all_predicate(vec!["timed out"])(&format!("{:?}", err)));
+    }
+
+    #[tokio::test]
+    async fn connect_timeout_keeps_pool_settings() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url)
+            .with_pool_settings(0, Duration::from_secs(1))
+            .with_connect_timeout(Duration::from_secs(1));
+        for _ in 0..2 {
+            client.send(|message| message.content("not pooled")).await.unwrap();
+        }
+        assert_eq!(server.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn edit_many_messages() {
+        let server = MockServer::start(|_, request| {
+            let id = request.uri.path().rsplit('/').next().unwrap().to_string();
+            let content = request.body_json()["content"].as_str().unwrap().to_string();
+            let body = serde_json::json!({"id": id, "channel_id": "9", "content": content});
+            response(StatusCode::OK, &body.to_string())
+        });
+        let client = WebhookClient::new(&server.url);
+
+        let mut first = EditMessage::new();
+        first.content("first status");
+        let mut second = EditMessage::new();
+        second.content("second status");
+        let results = client
+            .edit_many(vec![("11".to_string(), first), ("22".to_string(), second)])
+            .await;
+
+        assert_eq!(results.len(), 2);
+        let first = results[0].as_ref().unwrap();
+        assert_eq!((first.id.as_str(), first.content.as_str()), ("11", "first status"));
+        let second = results[1].as_ref().unwrap();
+        assert_eq!((second.id.as_str(), second.content.as_str()), ("22", "second status"));
+
+        let requests = server.requests();
+        assert!(requests.iter().all(|request| request.method == Method::PATCH));
+        let mut paths: Vec<_> = requests.iter().map(|request| request.uri.path().to_string()).collect();
+        paths.sort();
+        assert_eq!(paths, ["/api/webhooks/1234/token/messages/11", "/api/webhooks/1234/token/messages/22"]);
+    }
+
+    #[tokio::test]
+    async fn default_allowed_mentions() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&server.url).with_default_allowed_mentions(AllowedMentions::none());
+        client.send(|message| message.content("@everyone")).await.unwrap();
+        client
+            .send(|message| message.content("@everyone").allowed_mentions_preset(AllowedMentions::all()))
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].body_json()["allowed_mentions"]["parse"], serde_json::json!([]));
+        assert_eq!(
+            requests[1].body_json()["allowed_mentions"]["parse"],
+            serde_json::json!(["roles", "users", "everyone"])
+        );
+    }
+
+    #[tokio::test]
+    async fn send_reliable_retries_rate_limit() {
+        let server = MockServer::start(|index, _| match index {
+            0 => response(StatusCode::TOO_MANY_REQUESTS, r#"{"retry_after": 0.01}"#),
+            _ => response(StatusCode::OK, r#"{"id": "99", "channel_id": "9", "content": "critical"}"#),
+        });
+        let client = WebhookClient::new(&server.url);
+
+        let message = client.send_reliable(|message| message.content("critical")).await.unwrap();
+        assert_eq!(message.id, "99");
+        assert_eq!(message.content, "critical");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].uri.query(), Some("wait=true"));
+        let nonce = requests[0].body_json()["nonce"].clone();
+        assert!(nonce.is_string());
+        assert_eq!(requests[1].body_json()["nonce"], nonce);
+    }
+
+    #[tokio::test]
+    async fn send_reliable_gives_up() {
+        let server = MockServer::with_status(StatusCode::INTERNAL_SERVER_ERROR, "down");
+        let client = WebhookClient::new(&server.url).with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            retry_server_errors: true,
+            base_delay: Duration::from_millis(1),
+        });
+        let err = client.send_reliable(|message| message.content("lost")).await.unwrap_err();
+        assert_eq!(err.to_string(), "down");
+        assert_eq!(server.requests().len(), 3);
+
+        let server = MockServer::with_status(StatusCode::BAD_REQUEST, "invalid");
+        let client = WebhookClient::new(&server.url);
+        assert!(client.send_reliable(|message| message.content("lost")).await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn structured_errors() {
+        let server = MockServer::with_status(StatusCode::TOO_MANY_REQUESTS, r#"{"message": "You are being rate limited."}"#);
+        let client = WebhookClient::new(&server.url);
+        let err = client.send(|message| message.content("limited")).await.unwrap_err();
+        assert!(matches!(err, WebhookError::Api { status: StatusCode::TOO_MANY_REQUESTS, .. }));
+        let err = client.send_with_request_id(|message| message.content("limited"), "req-1").await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+
+        let err = client
+            .send(|message| message.content(&"a".repeat(Message::CONTENT_LEN_INTERVAL.max_allowed + 1)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WebhookError::Validation(ValidationError::IntervalViolation { .. })));
+
+        let err = WebhookClient::new("https://example.com/hook").send(|message| message.content("a")).await.unwrap_err();
+        assert!(matches!(err, WebhookError::InvalidUrl(_)));
+    }
+
+    #[tokio::test]
+    async fn send_with_custom_params() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let client = WebhookClient::new(&format!("{}?existing=1", server.url));
+        client
+            .send_with_params(
+                |message| message.content("params").thread_id("5"),
+                &[("with_components", "true"), ("note", "a b&c=d/é")],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.requests()[0].uri.query(),
+            Some("existing=1&thread_id=5&with_components=true&note=a%20b%26c%3Dd%2F%C3%A9")
+        );
     }
 
     fn test_is_send<T>(t: T)
@@ -0,0 +1,130 @@
+//! The error of the `WebhookClient` operations.
+
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use hyper::StatusCode;
+
+use crate::models::ValidationError;
+
+/// An error of a `WebhookClient` operation, see `WebhookResult`.
+#[derive(Debug)]
+pub enum WebhookError {
+    /// The message (or edit) violates the Discord API constraints, nothing was sent
+    Validation(ValidationError),
+    /// The webhook URL is not a (valid) webhook URL, the message describes why
+    InvalidUrl(String),
+    /// The HTTP request failed (connection, TLS, reading the response, ...)
+    Http(hyper::Error),
+    /// A request or response body could not be (de)serialized
+    Serialization(serde_json::Error),
+    /// Discord responded with an error status, `body` is the error message of the response
+    Api { status: StatusCode, body: String },
+    /// The request did not finish within the timeout
+    Timeout(Duration),
+    /// The request tagged with `request_id` (see `WebhookClient::send_with_request_id`) failed
+    Request {
+        request_id: String,
+        error: Box<WebhookError>,
+    },
+    /// Any other failure (e.g. reading a streamed attachment)
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl WebhookError {
+    /// The status of the Discord API response, for `Api` errors (also of tagged requests).
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            WebhookError::Api { status, .. } => Some(*status),
+            WebhookError::Request { error, .. } => error.status(),
+            _ => None,
+        }
+    }
+
+    /// Creates an `Other` error described by the `message`.
+    pub(crate) fn other(message: String) -> Self {
+        WebhookError::Other(message.into())
+    }
+}
+
+impl Display for WebhookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::Validation(error) => write!(f, "{}", error),
+            WebhookError::InvalidUrl(message) => write!(f, "{}", message),
+            WebhookError::Http(error) => write!(f, "{}", error),
+            WebhookError::Serialization(error) => write!(f, "{}", error),
+            WebhookError::Api { body, .. } => write!(f, "{}", body),
+            WebhookError::Timeout(timeout) => write!(f, "Request timed out after {:?}", timeout),
+            WebhookError::Request { request_id, error } => write!(f, "Request {} failed: {}", request_id, error),
+            WebhookError::Other(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WebhookError::Validation(error) => Some(error),
+            WebhookError::Http(error) => Some(error),
+            WebhookError::Serialization(error) => Some(error),
+            WebhookError::Request { error, .. } => Some(error.as_ref()),
+            WebhookError::Other(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for WebhookError {
+    fn from(error: ValidationError) -> Self {
+        WebhookError::Validation(error)
+    }
+}
+
+impl From<hyper::Error> for WebhookError {
+    fn from(error: hyper::Error) -> Self {
+        WebhookError::Http(error)
+    }
+}
+
+impl From<serde_json::Error> for WebhookError {
+    fn from(error: serde_json::Error) -> Self {
+        WebhookError::Serialization(error)
+    }
+}
+
+impl From<hyper::http::uri::InvalidUri> for WebhookError {
+    fn from(error: hyper::http::uri::InvalidUri) -> Self {
+        WebhookError::InvalidUrl(error.to_string())
+    }
+}
+
+impl From<hyper::http::Error> for WebhookError {
+    fn from(error: hyper::http::Error) -> Self {
+        WebhookError::Other(Box::new(error))
+    }
+}
+
+impl From<hyper::header::InvalidHeaderValue> for WebhookError {
+    fn from(error: hyper::header::InvalidHeaderValue) -> Self {
+        WebhookError::Other(Box::new(error))
+    }
+}
+
+impl From<std::io::Error> for WebhookError {
+    fn from(error: std::io::Error) -> Self {
+        WebhookError::Other(Box::new(error))
+    }
+}
+
+impl From<tokio::sync::AcquireError> for WebhookError {
+    fn from(error: tokio::sync::AcquireError) -> Self {
+        WebhookError::Other(Box::new(error))
+    }
+}
+
+impl From<tokio::task::JoinError> for WebhookError {
+    fn from(error: tokio::task::JoinError) -> Self {
+        WebhookError::Other(Box::new(error))
+    }
+}
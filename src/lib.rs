@@ -1,4 +1,26 @@
+#[cfg(all(feature = "client", feature = "rustls"))]
+compile_error!(
+    "features `client` (native TLS) and `rustls` are mutually exclusive, \
+    use `default-features = false, features = [\"rustls\"]` to build with rustls"
+);
+
 pub mod models;
 
-#[cfg(feature = "client")]
-pub mod client;
\ No newline at end of file
+pub mod cdn;
+
+pub mod router;
+
+#[cfg(any(feature = "client", feature = "rustls"))]
+pub mod client;
+
+#[cfg(any(feature = "client", feature = "rustls"))]
+pub mod error;
+
+#[cfg(any(feature = "client", feature = "rustls"))]
+pub mod rate_limit;
+
+#[cfg(any(feature = "client", feature = "rustls"))]
+pub mod log_sink;
+
+#[cfg(all(test, any(feature = "client", feature = "rustls")))]
+mod mock;
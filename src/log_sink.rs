@@ -0,0 +1,154 @@
+use tokio::task::JoinHandle;
+
+use crate::client::{WebhookClient, WebhookResult};
+use crate::models::Message;
+
+/// Buffers log lines and sends them to a webhook in batches, each message holding as many
+/// lines as fit its content. Full batches are sent in the background when the sink is used
+/// within a tokio runtime, otherwise they are kept until the next `flush`.
+///
+/// Buffered lines are lost if the sink is dropped, call `shutdown` before the program exits.
+pub struct LogSink {
+    client: WebhookClient,
+    buffer: String,
+    full_batches: Vec<String>,
+    in_flight: Vec<JoinHandle<WebhookResult<bool>>>,
+}
+
+impl LogSink {
+    pub fn new(client: WebhookClient) -> Self {
+        LogSink {
+            client,
+            buffer: String::new(),
+            full_batches: vec![],
+            in_flight: vec![],
+        }
+    }
+
+    /// Buffers a line, the buffered lines are sent once the next line would not fit
+    /// a message. Lines too long for a message are truncated.
+    ///
+    /// The send is spawned on the current tokio runtime. Called outside of a runtime, the batch
+    /// is kept and sent by the next `flush` (or `shutdown`) instead.
+    pub fn write_line(&mut self, line: &str) {
+        let max_len = Message::CONTENT_LEN_INTERVAL.max_allowed;
+        let mut end = line.len().min(max_len);
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        let line = &line[..end];
+
+        let separator_len = if self.buffer.is_empty() { 0 } else { 1 };
+        if self.buffer.len() + separator_len + line.len() > max_len {
+            let content = std::mem::take(&mut self.buffer);
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => self.in_flight.push(runtime.spawn(self.send_content(content))),
+                Err(_) => self.full_batches.push(content),
+            }
+        }
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(line);
+    }
+
+    /// Sends the buffered lines (and the full batches written outside of a runtime) right away.
+    pub async fn flush(&mut self) -> WebhookResult<()> {
+        for content in std::mem::take(&mut self.full_batches) {
+            self.send_content(content).await?;
+        }
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let content = std::mem::take(&mut self.buffer);
+        self.send_content(content).await?;
+        Ok(())
+    }
+
+    /// Sends the buffered lines and waits for the sends running in the background.
+    ///
+    /// # Return value
+    /// Error variant contains the first error of the sends
+    pub async fn shutdown(mut self) -> WebhookResult<()> {
+        let mut result = self.flush().await;
+        for send in self.in_flight.drain(..) {
+            let send_result = match send.await {
+                Ok(send_result) => send_result.map(|_| ()),
+                Err(join_error) => Err(join_error.into()),
+            };
+            result = result.and(send_result);
+        }
+        result
+    }
+
+    /// The future sending the `content`.
+    fn send_content(&self, content: String) -> impl std::future::Future<Output = WebhookResult<bool>> + Send + 'static {
+        let client = self.client.clone();
+        async move { client.send(|message| message.content(&content)).await }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LogSink;
+    use crate::client::WebhookClient;
+    use crate::mock::MockServer;
+    use hyper::StatusCode;
+
+    #[tokio::test]
+    async fn shutdown_sends_buffered_lines() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let mut sink = LogSink::new(WebhookClient::new(&server.url));
+
+        sink.write_line("first");
+        sink.write_line("second");
+        assert!(server.requests().is_empty());
+        sink.shutdown().await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].body_json()["content"], "first\nsecond");
+    }
+
+    #[tokio::test]
+    async fn full_batches_sent_in_background() {
+        let server = MockServer::with_status(StatusCode::NO_CONTENT, "");
+        let mut sink = LogSink::new(WebhookClient::new(&server.url));
+
+        let line = "a".repeat(999);
+        for _ in 0..5 {
+            sink.write_line(&line);
+        }
+        sink.flush().await.unwrap();
+        sink.shutdown().await.unwrap();
+
+        let mut lengths: Vec<usize> = server
+            .requests()
+            .iter()
+            .map(|request| request.body_json()["content"].as_str().unwrap().len())
+            .collect();
+        lengths.sort_unstable();
+        assert_eq!(lengths, vec![999, 1999, 1999]);
+    }
+
+    #[test]
+    fn full_batches_kept_outside_of_runtime() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async { MockServer::with_status(StatusCode::NO_CONTENT, "") });
+        let mut sink = LogSink::new(WebhookClient::new(&server.url));
+
+        let line = "a".repeat(999);
+        for _ in 0..5 {
+            sink.write_line(&line);
+        }
+        assert!(server.requests().is_empty());
+        runtime.block_on(sink.shutdown()).unwrap();
+
+        let lengths: Vec<usize> = server
+            .requests()
+            .iter()
+            .map(|request| request.body_json()["content"].as_str().unwrap().len())
+            .collect();
+        assert_eq!(lengths, vec![1999, 1999, 999]);
+    }
+}
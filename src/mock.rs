@@ -0,0 +1,137 @@
+//! A local HTTP server standing in for the Discord API in tests.
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Response, Server, StatusCode, Uri};
+
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A request received by the [`MockServer`].
+#[derive(Debug, Clone)]
+pub(crate) struct RecordedRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn body_json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).expect("request body is not JSON")
+    }
+}
+
+type Responder = dyn Fn(usize, &RecordedRequest) -> Response<Body> + Send + Sync;
+
+pub(crate) struct MockServer {
+    /// A webhook URL pointing to the server.
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    /// The highest number of requests received but not yet answered at the same time.
+    max_in_flight: Arc<AtomicUsize>,
+    /// The number of accepted connections.
+    connections: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    /// Starts a server answering every request by calling `respond` with the index of the
+    /// request and the request itself.
+    pub fn start<F>(respond: F) -> MockServer
+    where
+        F: Fn(usize, &RecordedRequest) -> Response<Body> + Send + Sync + 'static,
+    {
+        MockServer::start_with_delay(Duration::ZERO, respond)
+    }
+
+    /// Same as `start`, but every response is sent only after `delay` passes.
+    pub fn start_with_delay<F>(delay: Duration, respond: F) -> MockServer
+    where
+        F: Fn(usize, &RecordedRequest) -> Response<Body> + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests: Arc<Mutex<Vec<RecordedRequest>>> = Arc::new(Mutex::new(vec![]));
+        let respond: Arc<Responder> = Arc::new(respond);
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let service_requests = requests.clone();
+        let service_in_flight = Arc::new(AtomicUsize::new(0));
+        let service_max_in_flight = max_in_flight.clone();
+        let service_connections = connections.clone();
+        let make_service = make_service_fn(move |_| {
+            service_connections.fetch_add(1, Ordering::SeqCst);
+            let requests = service_requests.clone();
+            let respond = respond.clone();
+            let in_flight = service_in_flight.clone();
+            let max_in_flight = service_max_in_flight.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: hyper::Request<Body>| {
+                    let requests = requests.clone();
+                    let respond = respond.clone();
+                    let in_flight = in_flight.clone();
+                    let max_in_flight = max_in_flight.clone();
+                    async move {
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_in_flight.fetch_max(current, Ordering::SeqCst);
+                        let (parts, body) = request.into_parts();
+                        let body = hyper::body::to_bytes(body).await.unwrap().to_vec();
+                        let recorded = RecordedRequest {
+                            method: parts.method,
+                            uri: parts.uri,
+                            headers: parts.headers,
+                            body,
+                        };
+                        let index = {
+                            let mut requests = requests.lock().unwrap();
+                            requests.push(recorded.clone());
+                            requests.len() - 1
+                        };
+                        tokio::time::sleep(delay).await;
+                        let response = respond(index, &recorded);
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        Ok::<_, Infallible>(response)
+                    }
+                }))
+            }
+        });
+
+        let server = Server::from_tcp(listener).unwrap().serve(make_service);
+        tokio::spawn(server);
+
+        MockServer {
+            url: format!("http://{}/api/webhooks/1234/token", address),
+            requests,
+            max_in_flight,
+            connections,
+        }
+    }
+
+    /// Starts a server answering every request with the same status and body.
+    pub fn with_status(status: StatusCode, body: &'static str) -> MockServer {
+        MockServer::start(move |_, _| response(status, body))
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    /// The highest number of requests the server was handling at the same time.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+}
+
+pub(crate) fn response(status: StatusCode, body: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
@@ -1,8 +1,28 @@
-use serde::{Deserialize, Serialize, Serializer};
-use std::collections::HashSet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::ops::Sub;
+#[cfg(any(feature = "client", feature = "rustls"))]
+use std::pin::Pin;
+#[cfg(any(feature = "client", feature = "rustls"))]
+use std::sync::Mutex;
+#[cfg(any(feature = "client", feature = "rustls"))]
+use tokio::io::AsyncRead;
 type Snowflake = String;
 
+/// A closed interval `[min_allowed, max_allowed]`.
+///
+/// Used to describe the limits of the Discord API, but usable for custom validation as well,
+/// e.g. for enforcing a policy stricter than Discord's limits (see [`interval_check`]).
+///
+/// # Example
+/// ```
+/// # use webhook::models::{interval_check, Interval};
+/// const USERNAME_POLICY: Interval<usize> = Interval::from_min_max(3, 32);
+/// assert!(interval_check(&USERNAME_POLICY, &"bot".len(), "Username length").is_ok());
+/// assert_eq!(USERNAME_POLICY.clamp(50), 32);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Interval<T> {
     pub max_allowed: T,
     pub min_allowed: T,
@@ -21,13 +41,100 @@ impl<T: Ord> Interval<T> {
     }
 }
 
+impl<T: Ord + Copy> Interval<T> {
+    /// Restricts `value` to the interval.
+    pub fn clamp(&self, value: T) -> T {
+        value.max(self.min_allowed).min(self.max_allowed)
+    }
+
+    /// The interval of values contained in both `self` and `other`, None if there are none.
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        let min_allowed = self.min_allowed.max(other.min_allowed);
+        let max_allowed = self.max_allowed.min(other.max_allowed);
+        if min_allowed > max_allowed {
+            return None;
+        }
+        Some(Interval::from_min_max(min_allowed, max_allowed))
+    }
+
+    /// A copy of the interval with a different upper bound.
+    pub fn with_max(&self, max_allowed: T) -> Interval<T> {
+        Interval::from_min_max(self.min_allowed, max_allowed)
+    }
+
+    /// A copy of the interval with a different lower bound.
+    pub fn with_min(&self, min_allowed: T) -> Interval<T> {
+        Interval::from_min_max(min_allowed, self.max_allowed)
+    }
+}
+
+// `Ord` comparisons are not const, so the const check is implemented per integer type
+macro_rules! interval_contains_const {
+    ($($t:ty),*) => {
+        $(
+            impl Interval<$t> {
+                /// Same as `contains`, usable in const contexts (e.g. a `const` assertion).
+                pub const fn contains_const(&self, value: $t) -> bool {
+                    self.min_allowed <= value && value <= self.max_allowed
+                }
+            }
+        )*
+    };
+}
+
+interval_contains_const!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl<T: Ord + Copy + Sub<Output = T>> Interval<T> {
+    /// The difference between the upper and the lower bound.
+    pub fn span(&self) -> T {
+        self.max_allowed - self.min_allowed
+    }
+}
+
 macro_rules! interval_member {
 ($name:ident, $option_inner_t:ty, $lower_bound:expr, $upper_bound:expr) => {
         pub(crate) const $name : Interval<$option_inner_t> = Interval::from_min_max($lower_bound, $upper_bound);
     };
 }
 
-#[derive(Deserialize, Debug)]
+/// A secret webhook token. `Debug` and `Display` mask all but the last 4 characters, so that
+/// the token does not leak into logs.
+#[derive(Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct Token(String);
+
+impl Token {
+    const VISIBLE_SUFFIX_LEN: usize = 4;
+
+    pub fn new(token: &str) -> Self {
+        Token(token.to_string())
+    }
+
+    /// The raw token, handle with care.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let char_count = self.0.chars().count();
+        let suffix: String = if char_count > Token::VISIBLE_SUFFIX_LEN {
+            self.0.chars().skip(char_count - Token::VISIBLE_SUFFIX_LEN).collect()
+        } else {
+            String::new()
+        };
+        write!(f, "***{}", suffix)
+    }
+}
+
+impl std::fmt::Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Token({})", self)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct Webhook {
     pub id: Snowflake,
     #[serde(rename = "type")]
@@ -36,31 +143,357 @@ pub struct Webhook {
     pub channel_id: Snowflake,
     pub name: Option<String>,
     pub avatar: Option<String>,
-    pub token: String,
+    /// Empty for channel follower webhooks (type 2), which have no token
+    #[serde(default)]
+    pub token: Token,
     pub application_id: Option<Snowflake>,
+    /// The guild of the followed channel, set for channel follower webhooks (type 2)
+    pub source_guild: Option<WebhookSource>,
+    /// The followed channel, set for channel follower webhooks (type 2)
+    pub source_channel: Option<WebhookSource>,
+}
+
+/// A partial guild or channel followed by a channel follower webhook.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebhookSource {
+    pub id: Snowflake,
+    pub name: Option<String>,
+}
+
+/// A message as returned by Discord (e.g. after an edit).
+#[derive(Deserialize, Debug)]
+pub struct SentMessage {
+    pub id: Snowflake,
+    pub channel_id: Snowflake,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    pub edited_timestamp: Option<String>,
+}
+
+/// Webhook information enriched by the names of its channel and guild,
+/// see `WebhookClient::resolve_names`.
+#[derive(Debug)]
+pub struct WebhookInfo {
+    pub webhook: Webhook,
+    pub channel_name: Option<String>,
+    pub guild_name: Option<String>,
+}
+
+/// Upload limits of the target channel, the limit depends on the boost level of the guild.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitProfile {
+    /// A guild without boost perks
+    #[default]
+    Default,
+    /// A guild with boost level 2
+    Tier2,
+    /// A guild with boost level 3
+    Tier3,
+}
+
+impl LimitProfile {
+    /// The maximum combined size (in bytes) of the files uploaded with a message.
+    pub fn max_upload_size(&self) -> usize {
+        const MIB: usize = 1024 * 1024;
+        match self {
+            LimitProfile::Default => 25 * MIB,
+            LimitProfile::Tier2 => 50 * MIB,
+            LimitProfile::Tier3 => 100 * MIB,
+        }
+    }
+}
+
+/// The Discord endpoint a message is validated for, endpoints differ in the fields they accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationTarget {
+    /// Executing a webhook, the endpoint used by `WebhookClient`
+    #[default]
+    Execute,
+    /// A followup message of an interaction (sent with the interaction token)
+    InteractionFollowup,
+}
+
+/// The kind of webhook a message is built for, the kinds differ in the features they support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookKind {
+    /// A webhook created in a channel (or by the API), components are dropped by Discord
+    Incoming,
+    /// A webhook owned by an application, components are supported
+    Application,
+}
+
+/// Limits enforced by the validation which can be overridden, e.g. for a target implementing
+/// the Discord webhook API with different limits. Defaults to the Discord limits.
+///
+/// The limits cover the lengths and counts of the message content, the embeds and the action
+/// rows. The other constraints (custom ids, select menus, attachments, ...) and the eager checks
+/// of the builders (e.g. `Embed::field_chunked`) always use the Discord limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub content_len: Interval<usize>,
+    pub embed_count: Interval<usize>,
+    /// The combined length of the texts of all embeds of a message
+    pub embed_total_text_len: Interval<usize>,
+    pub embed_title_len: Interval<usize>,
+    pub embed_description_len: Interval<usize>,
+    pub embed_field_count: Interval<usize>,
+    pub embed_field_name_len: Interval<usize>,
+    pub embed_field_value_len: Interval<usize>,
+    pub embed_footer_text_len: Interval<usize>,
+    pub embed_author_name_len: Interval<usize>,
+    pub action_row_count: Interval<usize>,
+    pub action_row_button_count: Interval<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            content_len: Message::CONTENT_LEN_INTERVAL,
+            embed_count: Message::EMBED_COUNT_INTERVAL,
+            embed_total_text_len: Message::EMBED_TOTAL_TEXT_LEN_INTERVAL,
+            embed_title_len: Embed::TITLE_LEN_INTERVAL,
+            embed_description_len: Embed::DESCRIPTION_LEN_INTERVAL,
+            embed_field_count: Embed::FIELDS_LEN_INTERVAL,
+            embed_field_name_len: EmbedField::NAME_LEN_INTERVAL,
+            embed_field_value_len: EmbedField::VALUE_LEN_INTERVAL,
+            embed_footer_text_len: EmbedFooter::TEXT_LEN_INTERVAL,
+            embed_author_name_len: EmbedAuthor::NAME_LEN_INTERVAL,
+            action_row_count: Message::ACTION_ROW_COUNT_INTERVAL,
+            action_row_button_count: ActionRow::BUTTON_COUNT_INTERVAL,
+        }
+    }
+}
+
+impl Limits {
+    /// The limits which are the strictest of `self` and `other`, see `Interval::intersection`.
+    ///
+    /// # Return value
+    /// None if some limit of `self` and the same limit of `other` do not overlap
+    pub fn intersection(&self, other: &Limits) -> Option<Limits> {
+        Some(Limits {
+            content_len: self.content_len.intersection(&other.content_len)?,
+            embed_count: self.embed_count.intersection(&other.embed_count)?,
+            embed_total_text_len: self.embed_total_text_len.intersection(&other.embed_total_text_len)?,
+            embed_title_len: self.embed_title_len.intersection(&other.embed_title_len)?,
+            embed_description_len: self.embed_description_len.intersection(&other.embed_description_len)?,
+            embed_field_count: self.embed_field_count.intersection(&other.embed_field_count)?,
+            embed_field_name_len: self.embed_field_name_len.intersection(&other.embed_field_name_len)?,
+            embed_field_value_len: self.embed_field_value_len.intersection(&other.embed_field_value_len)?,
+            embed_footer_text_len: self.embed_footer_text_len.intersection(&other.embed_footer_text_len)?,
+            embed_author_name_len: self.embed_author_name_len.intersection(&other.embed_author_name_len)?,
+            action_row_count: self.action_row_count.intersection(&other.action_row_count)?,
+            action_row_button_count: self.action_row_button_count.intersection(&other.action_row_button_count)?,
+        })
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct MessageContext {
     custom_ids: HashSet<String>,
+    /// the animated flag of each (custom) emoji used in the message
+    emoji_animated_flags: HashMap<Snowflake, bool>,
     embeds_character_counter: usize,
     button_count_in_action_row: usize,
+    select_menu_count_in_action_row: usize,
+    limit_profile: LimitProfile,
+    limits: Limits,
+    target: ValidationTarget,
+    /// When set, `report` collects violations instead of propagating them
+    collect_violations: bool,
+    violations: Vec<ValidationError>,
+}
+
+/// A violation of the Discord API constraints found by validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A value (length, count, ...) of `field` lies outside of the allowed interval
+    IntervalViolation {
+        field: String,
+        value: String,
+        min_allowed: String,
+        max_allowed: String,
+    },
+    /// The custom id is used by more than one component of the message
+    DuplicateCustomId(String),
+    /// Any other violation, described by the message
+    Other(String),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::IntervalViolation {
+                field,
+                value,
+                min_allowed,
+                max_allowed,
+            } => write!(
+                f,
+                "{} ({}) not in the [{}, {}] interval",
+                field, value, min_allowed, max_allowed
+            ),
+            ValidationError::DuplicateCustomId(id) => {
+                write!(f, "Attempt to use the same custom ID ({}) twice!", id)
+            }
+            ValidationError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl ValidationError {
+    /// An actionable advice on fixing the violation, if there is one.
+    pub fn suggestion(&self) -> Option<String> {
+        let suggestion = match self {
+            ValidationError::IntervalViolation { field, .. } => match field.as_str() {
+                "Content length" => {
+                    "Split the content into multiple messages (e.g. with `LogSink`) or move it \
+                    into an embed description"
+                }
+                "Embed field value length" => "Split the value across multiple fields with `Embed::field_chunked`",
+                "Embed field count" => "Spread the fields across multiple embeds",
+                "Embed count" => "Spread the embeds across multiple messages",
+                "Character count across all embeds" => {
+                    "Spread the embeds across multiple messages (see `Message::remaining_embed_budget`)"
+                }
+                "Action row count" => "Spread the components across multiple messages",
+                "Button count" => "Spread the buttons across multiple action rows",
+                _ => return None,
+            },
+            ValidationError::DuplicateCustomId(_) => "Give every component of the message a unique custom id",
+            ValidationError::Other(_) => return None,
+        };
+        Some(suggestion.to_string())
+    }
+
+    /// The error message followed by the suggestion (if there is one) on the next line.
+    pub fn report(&self) -> String {
+        match self.suggestion() {
+            Some(suggestion) => format!("{}\n  suggestion: {}", self, suggestion),
+            None => self.to_string(),
+        }
+    }
+}
+
+/// A soft issue of a message, allowed by the Discord API but likely unintended.
+/// Warnings never block sending, see `Message::warnings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// More buttons of the same action row share the label
+    DuplicateButtonLabel { row: usize, label: String },
+    /// The description of the embed is longer than `WarningConfig::long_description_threshold`,
+    /// long descriptions render poorly on mobile
+    LongEmbedDescription { embed: usize, length: usize },
+}
+
+/// Configuration of the soft checks of `Message::warnings_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarningConfig {
+    /// Embed descriptions longer than this are reported
+    pub long_description_threshold: usize,
+}
+
+impl Default for WarningConfig {
+    fn default() -> Self {
+        WarningConfig {
+            long_description_threshold: 2048,
+        }
+    }
+}
+
+impl Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationWarning::DuplicateButtonLabel { row, label } => write!(
+                f,
+                "Multiple buttons of action row {} share the label ({})",
+                row, label
+            ),
+            ValidationWarning::LongEmbedDescription { embed, length } => write!(
+                f,
+                "The description of embed {} is long ({} characters) and may render poorly on mobile",
+                embed, length
+            ),
+        }
+    }
 }
 
-fn interval_check<T: Ord + Display>(
+/// Checks that `value_to_test` lies within `interval`.
+///
+/// # Return value
+/// Error variant contains an `IntervalViolation` naming `field_name`, the value and the interval
+pub fn interval_check<T: Ord + Display>(
     interval: &Interval<T>,
     value_to_test: &T,
     field_name: &str,
-) -> Result<(), String> {
+) -> Result<(), ValidationError> {
     if !interval.contains(value_to_test) {
-        return Err(format!(
-            "{} ({}) not in the [{}, {}] interval",
-            field_name, value_to_test, interval.min_allowed, interval.max_allowed
-        ));
+        return Err(ValidationError::IntervalViolation {
+            field: field_name.to_string(),
+            value: value_to_test.to_string(),
+            min_allowed: interval.min_allowed.to_string(),
+            max_allowed: interval.max_allowed.to_string(),
+        });
     }
     Ok(())
 }
 
+/// Escapes the Discord markdown characters (`*`, `_`, `` ` ``, `~`, `|` and `\`) by a backslash,
+/// so that text from external sources is displayed literally.
+///
+/// The escaping is applied everywhere, the text is not expected to contain intended
+/// formatting or code blocks (backslashes are displayed inside code blocks).
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '`' | '~' | '|' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Lists the Discord API limits enforced by the validation as `(name, min, max)` tuples
+/// (inclusive bounds), e.g. to render a reference table.
+pub fn all_limits() -> Vec<(&'static str, usize, usize)> {
+    fn limit(name: &'static str, interval: Interval<usize>) -> (&'static str, usize, usize) {
+        (name, interval.min_allowed, interval.max_allowed)
+    }
+    fn u8_limit(name: &'static str, interval: Interval<u8>) -> (&'static str, usize, usize) {
+        (name, interval.min_allowed as usize, interval.max_allowed as usize)
+    }
+
+    vec![
+        limit("content", Message::CONTENT_LEN_INTERVAL),
+        limit("action_row_count", Message::ACTION_ROW_COUNT_INTERVAL),
+        limit("attachment_count", Message::ATTACHMENT_COUNT_INTERVAL),
+        limit("embed_count", Message::EMBED_COUNT_INTERVAL),
+        limit("button_label", Message::LABEL_LEN_INTERVAL),
+        limit("custom_id", Message::CUSTOM_ID_LEN_INTERVAL),
+        limit("embed_total_text", Message::EMBED_TOTAL_TEXT_LEN_INTERVAL),
+        limit("embed_title", Embed::TITLE_LEN_INTERVAL),
+        limit("embed_description", Embed::DESCRIPTION_LEN_INTERVAL),
+        limit("embed_field_count", Embed::FIELDS_LEN_INTERVAL),
+        limit("embed_field_name", EmbedField::NAME_LEN_INTERVAL),
+        limit("embed_field_value", EmbedField::VALUE_LEN_INTERVAL),
+        limit("embed_footer_text", EmbedFooter::TEXT_LEN_INTERVAL),
+        limit("embed_author_name", EmbedAuthor::NAME_LEN_INTERVAL),
+        limit("action_row_button_count", ActionRow::BUTTON_COUNT_INTERVAL),
+        limit("link_button_url", Button::URL_LEN_INTERVAL),
+        limit("select_menu_option_count", SelectMenu::OPTION_COUNT_INTERVAL),
+        limit("select_menu_placeholder", SelectMenu::PLACEHOLDER_LEN_INTERVAL),
+        u8_limit("select_menu_min_values", SelectMenu::MIN_VALUES_INTERVAL),
+        u8_limit("select_menu_max_values", SelectMenu::MAX_VALUES_INTERVAL),
+        limit("select_option_label", SelectOption::LABEL_LEN_INTERVAL),
+        limit("select_option_value", SelectOption::VALUE_LEN_INTERVAL),
+        limit("select_option_description", SelectOption::DESCRIPTION_LEN_INTERVAL),
+    ]
+}
+
 impl MessageContext {
     /// Tries to register a custom id.
     ///
@@ -75,153 +508,1022 @@ impl MessageContext {
     ///
     /// # Return value
     /// Error variant contains an error message
-    fn register_custom_id(&mut self, id: &str) -> Result<(), String> {
+    fn register_custom_id(&mut self, id: &str) -> Result<(), ValidationError> {
         interval_check(
             &Message::CUSTOM_ID_LEN_INTERVAL,
             &id.len(),
             "Custom ID length")?;
 
-        if !self.custom_ids.insert(id.to_string()) {
-            return Err(format!("Attempt to use the same custom ID ({}) twice!", id));
-        }
-        Ok(())
+        if !self.custom_ids.insert(id.to_string()) {
+            return Err(ValidationError::DuplicateCustomId(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Tries to register an emoji used by a component.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (the same emoji is used with conflicting
+    /// `animated` flags)
+    fn register_emoji(&mut self, emoji: &PartialEmoji) -> Result<(), ValidationError> {
+        let animated = match emoji.animated {
+            Some(animated) => animated,
+            None => return Ok(()),
+        };
+        match self.emoji_animated_flags.insert(emoji.id.clone(), animated) {
+            Some(registered) if registered != animated => Err(ValidationError::Other(format!(
+                "Emoji ({}) used with conflicting animated flags!",
+                emoji.id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Tries to register an Embed
+    ///
+    /// # Return value
+    ///
+    /// None on no error. Some(String) containing the reason for failure.
+    pub fn register_embed(&mut self, embed: &Embed) -> Result<(), ValidationError> {
+        self.embeds_character_counter += embed.character_count();
+
+        interval_check(
+            &self.limits.embed_total_text_len,
+            &self.embeds_character_counter,
+            "Character count across all embeds")?;
+        Ok(())
+    }
+
+    pub(crate) fn new() -> MessageContext {
+        MessageContext::with_limit_profile(LimitProfile::default())
+    }
+
+    pub(crate) fn with_limit_profile(limit_profile: LimitProfile) -> MessageContext {
+        MessageContext {
+            custom_ids: HashSet::new(),
+            emoji_animated_flags: HashMap::new(),
+            button_count_in_action_row: 0,
+            select_menu_count_in_action_row: 0,
+            embeds_character_counter: 0,
+            limit_profile,
+            limits: Limits::default(),
+            target: ValidationTarget::default(),
+            collect_violations: false,
+            violations: vec![],
+        }
+    }
+
+    /// Validates against `limits` instead of the Discord limits.
+    pub(crate) fn with_limits(mut self, limits: Limits) -> MessageContext {
+        self.limits = limits;
+        self
+    }
+
+    /// Creates a context which collects all violations instead of stopping at the first one.
+    /// The violations are retrieved by `into_violations`.
+    pub(crate) fn collecting() -> MessageContext {
+        let mut context = MessageContext::new();
+        context.collect_violations = true;
+        context
+    }
+
+    /// Handles the result of a single check of a composite component.
+    ///
+    /// # Return value
+    /// In the (default) short-circuiting mode, the result itself. In the collecting mode, the
+    /// violation is stored and `Ok` is returned so that the validation continues.
+    fn report(&mut self, result: Result<(), ValidationError>) -> Result<(), ValidationError> {
+        match result {
+            Err(violation) if self.collect_violations => {
+                self.violations.push(violation);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// The violations collected by a context created by `collecting`, `result` is the result
+    /// of the top-level check.
+    pub(crate) fn into_violations(
+        mut self,
+        result: Result<(), ValidationError>,
+    ) -> Vec<ValidationError> {
+        if let Err(violation) = result {
+            self.violations.push(violation);
+        }
+        self.violations
+    }
+
+    /// Checks the attachments of a message against the active `LimitProfile`.
+    ///
+    /// # Return value
+    /// Error variant contains an error message
+    pub(crate) fn register_attachments(&mut self, attachments: &[Attachment]) -> Result<(), ValidationError> {
+        interval_check(
+            &Message::ATTACHMENT_COUNT_INTERVAL,
+            &attachments.len(),
+            "Attachment count")?;
+
+        let total_size: usize = attachments.iter().map(|a| a.data.len()).sum();
+        interval_check(
+            &Interval::from_min_max(0, self.limit_profile.max_upload_size()),
+            &total_size,
+            "Combined attachment size (bytes)")?;
+        Ok(())
+    }
+
+    /// Tries to register a button using the button's custom id.
+    ///
+    /// # Return value
+    /// Error variant contains an error message
+    ///
+    /// # Note
+    /// Subsequent calls register other components semantically in the same action row.
+    /// To register components in a new action row, use the `register_action_row` function before
+    /// calling this function
+    fn register_button(&mut self, id: &str) -> Result<(), ValidationError> {
+        self.register_custom_id(id)?;
+        self.button_count_in_action_row += 1;
+
+        if self.select_menu_count_in_action_row > 0 {
+            return Err(ValidationError::Other("A button cannot share an action row with a select menu!".to_string()));
+        }
+
+        interval_check(
+            &self.limits.action_row_button_count,
+            &self.button_count_in_action_row,
+            "Button count")?;
+        Ok(())
+    }
+
+    /// Tries to register a select menu using the menu's custom id.
+    ///
+    /// # Return value
+    /// Error variant contains an error message
+    ///
+    /// # Note
+    /// A select menu must be the only component of an action row. The same action row semantics
+    /// as in `register_button` apply.
+    fn register_select_menu(&mut self, id: &str) -> Result<(), ValidationError> {
+        self.register_custom_id(id)?;
+        self.select_menu_count_in_action_row += 1;
+
+        if self.button_count_in_action_row > 0 {
+            return Err(ValidationError::Other("A select menu cannot share an action row with buttons!".to_string()));
+        }
+
+        if self.select_menu_count_in_action_row > 1 {
+            return Err(ValidationError::Other("An action row cannot contain more than one select menu!".to_string()));
+        }
+        Ok(())
+    }
+    /// Switches the context to register components logically in a "new" action row.
+    ///
+    /// # Watch out!
+    /// This function shall be called only once per one action row. (due to the lack of action row
+    /// identification)
+    fn register_action_row(&mut self) {
+        self.button_count_in_action_row = 0;
+        self.select_menu_count_in_action_row = 0;
+    }
+}
+
+/// a macro which generates setter functions of `IncomingWebhookMessage` that delegate their
+/// inputs to the wrapped message
+macro_rules! message_delegation {
+    ($($(#[$meta:meta])* $name:ident($($arg:ident: $t:ty),*);)*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name(&mut self, $($arg: $t),*) -> &mut Self {
+                self.message.$name($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+/// A message for an incoming webhook, see `Message::for_incoming_webhook`. It has the builder
+/// methods of `Message` except for the ones adding components, which incoming webhooks do not
+/// support. Dereferences to the built `Message`, e.g. to send it by `WebhookClient::send_message`.
+#[derive(Debug)]
+pub struct IncomingWebhookMessage {
+    message: Message,
+}
+
+impl IncomingWebhookMessage {
+    message_delegation! {
+        content(content: &str);
+        /// Sets the content with the markdown characters escaped, see `escape_markdown`.
+        content_escaped(content: &str);
+        /// Uploads a file with the message. Embeds may reference it as `attachment://{filename}`.
+        attachment(filename: &str, data: Vec<u8>);
+        username(username: &str);
+        /// Sets a message flag (one of the `Message::FLAG_*` constants), keeping the flags already set.
+        flag(flag: u64);
+        avatar_url(avatar_url: &str);
+        tts(tts: bool);
+        /// Makes the message a reply to the message `message_id`, see `Message::reply_to`.
+        reply_to(message_id: &str);
+        /// Posts the message to a thread of the webhook's channel, see `Message::thread_id`.
+        thread_id(thread_id: &str);
+        /// Applies a tag (by its id) to the thread created in a forum channel.
+        applied_tag(tag_id: &str);
+        /// Adds an already built embed.
+        add_embed(embed: Embed);
+        /// Replaces all embeds of the message.
+        embeds(embeds: Vec<Embed>);
+        allow_mentions(
+            parse: Option<Vec<AllowedMention>>,
+            roles: Option<Vec<Snowflake>>,
+            users: Option<Vec<Snowflake>>,
+            replied_user: bool
+        );
+        /// Sets the allowed mentions, e.g. to a preset such as `AllowedMentions::none()`.
+        allowed_mentions_preset(allowed_mentions: AllowedMentions);
+    }
+
+    /// Uploads a file streamed from `reader`, see `Attachment::from_reader`.
+    #[cfg(any(feature = "client", feature = "rustls"))]
+    pub fn attachment_stream<R>(&mut self, filename: &str, reader: R, len: usize) -> &mut Self
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.message.attachment_stream(filename, reader, len);
+        self
+    }
+
+    pub fn embed<Func>(&mut self, func: Func) -> &mut Self
+    where
+        Func: Fn(&mut Embed) -> &mut Embed,
+    {
+        self.message.embed(func);
+        self
+    }
+
+    /// Mutable access to an already added embed, None if `index` is out of bounds.
+    pub fn embed_mut(&mut self, index: usize) -> Option<&mut Embed> {
+        self.message.embed_mut(index)
+    }
+
+    /// Appends a field to the most recently added embed, see `Message::add_field_to_last_embed`.
+    pub fn add_field_to_last_embed(
+        &mut self,
+        name: &str,
+        value: &str,
+        inline: bool,
+    ) -> Result<&mut Self, ValidationError> {
+        self.message.add_field_to_last_embed(name, value, inline)?;
+        Ok(self)
+    }
+
+    /// The built message.
+    pub fn into_message(self) -> Message {
+        self.message
+    }
+}
+
+impl std::ops::Deref for IncomingWebhookMessage {
+    type Target = Message;
+
+    fn deref(&self) -> &Message {
+        &self.message
+    }
+}
+
+impl From<IncomingWebhookMessage> for Message {
+    fn from(message: IncomingWebhookMessage) -> Self {
+        message.message
+    }
+}
+
+/// A reference to the message a message replies to.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MessageReference {
+    pub message_id: Snowflake,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Message {
+    pub content: Option<String>,
+    pub username: Option<String>,
+    pub avatar_url: Option<String>,
+    pub tts: bool,
+    pub embeds: Vec<Embed>,
+    #[serde(rename = "allowed_mentions")]
+    pub allow_mentions: Option<AllowedMentions>,
+    #[serde(rename = "components")]
+    pub action_rows: Vec<ActionRow>,
+    /// Files uploaded with the message, only their metadata is part of the JSON payload
+    #[serde(
+        serialize_with = "serialize_attachments",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub attachments: Vec<Attachment>,
+    /// The thread to post the message to, sent as the `thread_id` query parameter
+    #[serde(skip)]
+    pub thread_id: Option<Snowflake>,
+    /// A value identifying the send, attempts carrying the same nonce create only one message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Message flags (bit field), see the `FLAG_*` constants
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<u64>,
+    /// The tags applied to the thread created in a forum channel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied_tags: Option<Vec<Snowflake>>,
+    /// The message the message replies to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_reference: Option<MessageReference>,
+    /// The kind of webhook the message is built for, None if not known
+    #[serde(skip)]
+    webhook_kind: Option<WebhookKind>,
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Self {
+            content: None,
+            username: None,
+            avatar_url: None,
+            tts: false,
+            embeds: vec![],
+            allow_mentions: None,
+            action_rows: vec![],
+            attachments: vec![],
+            thread_id: None,
+            nonce: None,
+            flags: None,
+            applied_tags: None,
+            message_reference: None,
+            webhook_kind: None,
+        }
+    }
+
+    /// A message for an application webhook, which supports components.
+    pub fn for_application_webhook() -> Self {
+        let mut message = Message::new();
+        message.webhook_kind = Some(WebhookKind::Application);
+        message
+    }
+
+    /// A message for an incoming webhook. Incoming webhooks do not support components, the
+    /// returned builder has no methods adding action rows. Action rows added after converting
+    /// it into a `Message` are rejected by the validation instead of Discord silently dropping
+    /// the components.
+    pub fn for_incoming_webhook() -> IncomingWebhookMessage {
+        let mut message = Message::new();
+        message.webhook_kind = Some(WebhookKind::Incoming);
+        IncomingWebhookMessage { message }
+    }
+
+    /// The kind of webhook the message is built for, None if not known.
+    pub fn webhook_kind(&self) -> Option<WebhookKind> {
+        self.webhook_kind
+    }
+
+    /// A placeholder message (an hourglass followed by `text` and a disabled "Loading..."
+    /// button) to be sent before the real content is edited in, see `WebhookClient::edit_message`.
+    pub fn loading(text: &str) -> Message {
+        let mut message = Message::new();
+        message.content(&format!("\u{23f3} {}", text)).action_row(|row| {
+            row.regular_button(|button| {
+                button
+                    .style(NonLinkButtonStyle::Secondary)
+                    .label("Loading...")
+                    .custom_id("loading")
+                    .disabled(true)
+            })
+        });
+        message
+    }
+
+    pub fn content(&mut self, content: &str) -> &mut Self {
+        self.content = Some(content.to_owned());
+        self
+    }
+
+    /// Sets the content with the markdown characters escaped, see `escape_markdown`.
+    pub fn content_escaped(&mut self, content: &str) -> &mut Self {
+        self.content(&escape_markdown(content))
+    }
+
+    /// Uploads a file with the message. Embeds may reference it as `attachment://{filename}`.
+    pub fn attachment(&mut self, filename: &str, data: Vec<u8>) -> &mut Self {
+        self.attachments.push(Attachment::new(filename, data));
+        self
+    }
+
+    /// Uploads a file streamed from `reader`, see `Attachment::from_reader`.
+    #[cfg(any(feature = "client", feature = "rustls"))]
+    pub fn attachment_stream<R>(&mut self, filename: &str, reader: R, len: usize) -> &mut Self
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.attachments.push(Attachment::from_reader(filename, reader, len));
+        self
+    }
+
+    pub fn username(&mut self, username: &str) -> &mut Self {
+        self.username = Some(username.to_owned());
+        self
+    }
+
+    /// Sets a message flag (one of the `FLAG_*` constants), keeping the flags already set.
+    pub fn flag(&mut self, flag: u64) -> &mut Self {
+        self.flags = Some(self.flags.unwrap_or(0) | flag);
+        self
+    }
+
+    /// Whether the message flag (one of the `FLAG_*` constants) is set.
+    pub fn has_flag(&self, flag: u64) -> bool {
+        self.flags.is_some_and(|flags| flags & flag == flag)
+    }
+
+    /// Do not include any embeds when serializing the message.
+    pub const FLAG_SUPPRESS_EMBEDS: u64 = 1 << 2;
+    /// The message does not trigger push and desktop notifications.
+    pub const FLAG_SUPPRESS_NOTIFICATIONS: u64 = 1 << 12;
+    /// The message is laid out by components only, `content` and `embeds` cannot be used.
+    pub const FLAG_IS_COMPONENTS_V2: u64 = 1 << 15;
+    /// The message is visible only to the user who invoked the interaction, allowed only for
+    /// interaction followups (see `check_for`).
+    pub const FLAG_EPHEMERAL: u64 = 1 << 6;
+
+    pub fn avatar_url(&mut self, avatar_url: &str) -> &mut Self {
+        self.avatar_url = Some(avatar_url.to_owned());
+        self
+    }
+
+    pub fn tts(&mut self, tts: bool) -> &mut Self {
+        self.tts = tts;
+        self
+    }
+
+    /// Makes the message a reply to the message `message_id`, see `AllowedMentions::replied_user`
+    /// for pinging its author.
+    pub fn reply_to(&mut self, message_id: &str) -> &mut Self {
+        self.message_reference = Some(MessageReference {
+            message_id: message_id.to_owned(),
+        });
+        self
+    }
+
+    /// Posts the message to a thread of the webhook's channel. `WebhookClient::send_to_thread`
+    /// takes precedence over this value.
+    pub fn thread_id(&mut self, thread_id: &str) -> &mut Self {
+        self.thread_id = Some(thread_id.to_owned());
+        self
+    }
+
+    /// Applies a tag (by its id) to the thread created in a forum channel, see
+    /// `WebhookClient::check_applied_tags`.
+    pub fn applied_tag(&mut self, tag_id: &str) -> &mut Self {
+        self.applied_tags.get_or_insert_with(Vec::new).push(tag_id.to_owned());
+        self
+    }
+
+    pub fn embed<Func>(&mut self, func: Func) -> &mut Self
+    where
+        Func: Fn(&mut Embed) -> &mut Embed,
+    {
+        let mut embed = Embed::new();
+        func(&mut embed);
+        self.embeds.push(embed);
+
+        self
+    }
+
+    /// Adds an already built embed.
+    pub fn add_embed(&mut self, embed: Embed) -> &mut Self {
+        self.embeds.push(embed);
+        self
+    }
+
+    /// Replaces all embeds of the message, e.g. by embeds built with `Embed::build`.
+    pub fn embeds(&mut self, embeds: Vec<Embed>) -> &mut Self {
+        self.embeds = embeds;
+        self
+    }
+
+    /// Validates the message, reporting all violations instead of stopping at the first one.
+    ///
+    /// # Return value
+    /// All found violations, empty if the message is valid
+    pub fn check_all(&self) -> Vec<ValidationError> {
+        let mut context = MessageContext::collecting();
+        let result = self.check_compatibility(&mut context);
+        context.into_violations(result)
+    }
+
+    /// Validates the message for the `target` endpoint. `check_compatibility` (and thus sending
+    /// by `WebhookClient`) validates for `ValidationTarget::Execute`.
+    ///
+    /// # Return value
+    /// Error variant contains an error message
+    pub fn check_for(&self, target: ValidationTarget) -> Result<(), ValidationError> {
+        let mut context = MessageContext::new();
+        context.target = target;
+        self.check_compatibility(&mut context)
+    }
+
+    /// Validates the message and serializes it only if it is valid, e.g. for snapshot tests
+    /// of message templates.
+    ///
+    /// # Return value
+    /// The JSON payload of the message. Error variant contains an error message
+    pub fn to_validated_json(&self) -> Result<String, ValidationError> {
+        self.check_compatibility(&mut MessageContext::new())?;
+        serde_json::to_string(self)
+            .map_err(|err| ValidationError::Other(format!("The message cannot be serialized: {}", err)))
+    }
+
+    /// Validates the message against custom `limits` instead of the Discord limits, see `Limits`.
+    ///
+    /// # Return value
+    /// Error variant contains an error message
+    pub fn check_with_limits(&self, limits: Limits) -> Result<(), ValidationError> {
+        self.check_compatibility(&mut MessageContext::new().with_limits(limits))
+    }
+
+    /// The number of characters which can still be added to the embeds before reaching the
+    /// total limit (see `EMBED_TOTAL_TEXT_LEN_INTERVAL`), negative if the limit is exceeded.
+    pub fn remaining_embed_budget(&self) -> i64 {
+        let used: usize = self.embeds.iter().map(Embed::character_count).sum();
+        Message::EMBED_TOTAL_TEXT_LEN_INTERVAL.max_allowed as i64 - used as i64
+    }
+
+    /// Runs all checks of the message at once: the Discord API constraints (see `check_all`)
+    /// and the size of the whole request (JSON payload and attachments) against the default
+    /// upload limit. Useful e.g. to validate message templates in CI.
+    ///
+    /// # Return value
+    /// Error variant contains all found violations
+    pub fn preflight(&self) -> Result<(), Vec<ValidationError>> {
+        let mut violations = self.check_all();
+        match serde_json::to_vec(self) {
+            Ok(payload) => {
+                let attachments_size: usize = self.attachments.iter().map(|a| a.data.len()).sum();
+                let result = interval_check(
+                    &Interval::from_min_max(0, LimitProfile::default().max_upload_size()),
+                    &(payload.len() + attachments_size),
+                    "Request size (bytes)");
+                violations.extend(result.err());
+            }
+            Err(err) => violations.push(ValidationError::Other(format!(
+                "The message cannot be serialized: {}",
+                err
+            ))),
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// A hash of the message (its JSON payload, the data of its attachments and its thread id),
+    /// stable across runs and platforms, e.g. for deduplication and caching. Streamed attachments
+    /// contribute only their length.
+    pub fn content_hash(&self) -> u64 {
+        // 64-bit FNV-1a, unlike `DefaultHasher` it is guaranteed not to change
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        fn hash_bytes(hash: u64, bytes: &[u8]) -> u64 {
+            bytes
+                .iter()
+                .fold(hash, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(PRIME))
+        }
+
+        // the serialization of a message cannot fail, it contains no maps with non-string keys
+        let payload = serde_json::to_vec(self).unwrap_or_default();
+        let mut hash = hash_bytes(OFFSET_BASIS, &payload);
+        for attachment in self.attachments.iter() {
+            hash = match &attachment.data {
+                AttachmentData::Bytes(data) => hash_bytes(hash, data),
+                #[cfg(any(feature = "client", feature = "rustls"))]
+                AttachmentData::Stream(stream) => hash_bytes(hash, &(stream.len as u64).to_le_bytes()),
+            };
+        }
+        // the thread id is sent in the query, not in the payload
+        if let Some(thread_id) = &self.thread_id {
+            hash = hash_bytes(hash, thread_id.as_bytes());
+        }
+        hash
+    }
+
+    /// Builds a message using the builder `func` and validates it, see `check_all`.
+    ///
+    /// # Return value
+    /// The message if it is valid, all found violations otherwise
+    pub fn try_build<Func>(func: Func) -> Result<Message, Vec<ValidationError>>
+    where
+        Func: FnOnce(&mut Message) -> &mut Message,
+    {
+        let mut message = Message::new();
+        func(&mut message);
+        let violations = message.check_all();
+        if violations.is_empty() {
+            Ok(message)
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Checks the message for soft issues which do not prevent sending, using the default
+    /// `WarningConfig`.
+    ///
+    /// # Return value
+    /// All found warnings, empty if there are none
+    pub fn warnings(&self) -> Vec<ValidationWarning> {
+        self.warnings_with(&WarningConfig::default())
+    }
+
+    /// Same as `warnings`, with custom thresholds.
+    pub fn warnings_with(&self, config: &WarningConfig) -> Vec<ValidationWarning> {
+        let mut warnings = vec![];
+        for (index, embed) in self.embeds.iter().enumerate() {
+            let length = embed.description.as_ref().map_or(0, |d| d.len());
+            if length > config.long_description_threshold {
+                warnings.push(ValidationWarning::LongEmbedDescription { embed: index, length });
+            }
+        }
+        for (index, row) in self.action_rows.iter().enumerate() {
+            let mut labels = HashSet::new();
+            let mut reported = HashSet::new();
+            for label in row.button_labels() {
+                if !labels.insert(label) && reported.insert(label) {
+                    warnings.push(ValidationWarning::DuplicateButtonLabel {
+                        row: index,
+                        label: label.to_string(),
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Mutable access to an already added embed, None if `index` is out of bounds.
+    pub fn embed_mut(&mut self, index: usize) -> Option<&mut Embed> {
+        self.embeds.get_mut(index)
+    }
+
+    /// Appends a field to the most recently added embed.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (no embed present, embed field count exceeded)
+    pub fn add_field_to_last_embed(
+        &mut self,
+        name: &str,
+        value: &str,
+        inline: bool,
+    ) -> Result<&mut Self, ValidationError> {
+        let embed = match self.embeds.last_mut() {
+            Some(embed) => embed,
+            None => return Err(ValidationError::Other("Cannot add a field, the message has no embeds!".to_string())),
+        };
+        interval_check(
+            &Embed::FIELDS_LEN_INTERVAL,
+            &(embed.fields.len() + 1),
+            "Embed field count")?;
+
+        embed.fields.push(EmbedField::new(name, value, inline));
+        Ok(self)
+    }
+
+    pub fn action_row<Func>(&mut self, func: Func) -> &mut Self
+    where
+        Func: Fn(&mut ActionRow) -> &mut ActionRow,
+    {
+        let mut row = ActionRow::new();
+        func(&mut row);
+        self.action_rows.push(row);
+
+        self
     }
 
-    /// Tries to register an Embed
+    /// Adds an already built action row (e.g. `ActionRow::confirm_cancel`).
+    pub fn add_action_row(&mut self, row: ActionRow) -> &mut Self {
+        self.action_rows.push(row);
+        self
+    }
+
+    /// Removes the action row at `index`, the following rows are shifted.
     ///
     /// # Return value
-    ///
-    /// None on no error. Some(String) containing the reason for failure.
-    pub fn register_embed(&mut self, embed: &Embed) -> Result<(), String> {
-
-        self.embeds_character_counter += embed.title.as_ref().map_or(0, |s| s.len());
-        self.embeds_character_counter += embed.description.as_ref().map_or(0, |s| s.len());
-        self.embeds_character_counter += embed.footer.as_ref().map_or(0, |f| f.text.len());
-        self.embeds_character_counter += embed.author.as_ref().map_or(0, |a| a.name.len());
+    /// Error variant contains an error message (index out of bounds)
+    pub fn remove_action_row(&mut self, index: usize) -> Result<(), ValidationError> {
+        if index >= self.action_rows.len() {
+            return Err(ValidationError::Other(format!(
+                "Cannot remove action row {}, the message has {} action rows!",
+                index,
+                self.action_rows.len()
+            )));
+        }
+        self.action_rows.remove(index);
+        Ok(())
+    }
 
-        embed.fields.iter().for_each(|f| {
-            self.embeds_character_counter += f.name.len() + f.value.len();
-        });
+    pub fn action_row_count(&self) -> usize {
+        self.action_rows.len()
+    }
 
-        interval_check(
-            &Message::EMBED_TOTAL_TEXT_LEN_INTERVAL,
-            &self.embeds_character_counter,
-            "Character count across all embeds")?;
-        Ok(())
+    /// The emojis of all buttons and select options of the message, in order of appearance.
+    pub fn emojis(&self) -> Vec<&PartialEmoji> {
+        self.action_rows
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .flat_map(|component| match component {
+                NonCompositeComponent::Button(button) => button.emoji.iter().collect::<Vec<_>>(),
+                NonCompositeComponent::SelectMenu(menu) => {
+                    menu.options.iter().filter_map(|option| option.emoji.as_ref()).collect()
+                }
+            })
+            .collect()
     }
 
-    pub(crate) fn new() -> MessageContext {
-        MessageContext {
-            custom_ids: HashSet::new(),
-            button_count_in_action_row: 0,
-            embeds_character_counter: 0
+    /// Disables the buttons and select menus whose custom id matches `predicate`, e.g. the option
+    /// the user just picked. Link buttons have no custom id and are left as they are.
+    pub fn disable_components_where<Pred>(&mut self, predicate: Pred) -> &mut Self
+    where
+        Pred: Fn(&str) -> bool,
+    {
+        for component in self.action_rows.iter_mut().flat_map(|row| row.components.iter_mut()) {
+            let (custom_id, disabled) = match component {
+                NonCompositeComponent::Button(button) => (&button.custom_id, &mut button.disabled),
+                NonCompositeComponent::SelectMenu(menu) => (&menu.custom_id, &mut menu.disabled),
+            };
+            if custom_id.as_deref().is_some_and(&predicate) {
+                *disabled = Some(true);
+            }
         }
+        self
     }
 
-    /// Tries to register a button using the button's custom id.
+    /// Appends the action rows read from a JSON array (e.g. stored components), see
+    /// `ActionRow::from_json`. The rows are validated together before any of them is added.
     ///
     /// # Return value
-    /// Error variant contains an error message
+    /// Error variant contains an error message (malformed JSON, unknown component type or
+    /// failed validation)
+    pub fn components_from_json(&mut self, json: &str) -> Result<&mut Self, ValidationError> {
+        let rows: Vec<ActionRow> = serde_json::from_str(json)
+            .map_err(|e| ValidationError::Other(format!("Invalid components JSON: {}", e)))?;
+        let mut context = MessageContext::with_limit_profile(LimitProfile::default());
+        for row in rows.iter() {
+            row.check_deserialized(&mut context)?;
+        }
+        self.action_rows.extend(rows);
+        Ok(self)
+    }
+
+    /// The edit turning this (sent) message into `other`, only the fields that differ are set.
     ///
-    /// # Note
-    /// Subsequent calls register other components semantically in the same action row.
-    /// To register components in a new action row, use the `register_action_row` function before
-    /// calling this function
-    fn register_button(&mut self, id: &str) -> Result<(), String> {
-        self.register_custom_id(id)?;
-        self.button_count_in_action_row += 1;
+    /// Removed content is cleared by setting it to an empty string, removed embeds and
+    /// components by setting them to an empty vector. The allowed mentions are only set if
+    /// `other` sets them, since Discord has no way to reset them.
+    pub fn diff(&self, other: &Message) -> EditMessage {
+        let mut edit = EditMessage::new();
+        if self.content != other.content {
+            edit.content = Some(other.content.clone().unwrap_or_default());
+        }
+        if self.embeds != other.embeds {
+            edit.embeds = Some(other.embeds.clone());
+        }
+        if self.allow_mentions != other.allow_mentions {
+            edit.allow_mentions = other.allow_mentions.clone();
+        }
+        if self.action_rows != other.action_rows {
+            edit.action_rows = Some(other.action_rows.clone());
+        }
+        edit.thread_id = other.thread_id.clone();
+        edit
+    }
 
-        interval_check(
-            &ActionRow::BUTTON_COUNT_INTERVAL,
-            &self.button_count_in_action_row,
-            "Button count")?;
-        Ok(())
+    interval_member!(CONTENT_LEN_INTERVAL, usize, 0, 2000);
+    interval_member!(ACTION_ROW_COUNT_INTERVAL, usize, 0, 5);
+    interval_member!(ATTACHMENT_COUNT_INTERVAL, usize, 0, 10);
+    interval_member!(EMBED_COUNT_INTERVAL, usize, 0, 10);
+    interval_member!(LABEL_LEN_INTERVAL, usize, 0, 80);
+    interval_member!(CUSTOM_ID_LEN_INTERVAL, usize, 1, 100);
+    // Additionally, the combined sum of characters in all title, description, field.name,
+    // field.value, footer.text, and author.name fields across all embeds attached to a message
+    // must not exceed 6000 characters.
+    interval_member!(EMBED_TOTAL_TEXT_LEN_INTERVAL, usize, 0, 6000);
+
+    pub fn allow_mentions(
+        &mut self,
+        parse: Option<Vec<AllowedMention>>,
+        roles: Option<Vec<Snowflake>>,
+        users: Option<Vec<Snowflake>>,
+        replied_user: bool,
+    ) -> &mut Self {
+        self.allow_mentions = Some(AllowedMentions::new(parse, roles, users, replied_user));
+        self
     }
-    /// Switches the context to register components logically in a "new" action row.
+
+    /// Sets the allowed mentions, e.g. to a preset such as `AllowedMentions::none()`.
+    pub fn allowed_mentions_preset(&mut self, allowed_mentions: AllowedMentions) -> &mut Self {
+        self.allow_mentions = Some(allowed_mentions);
+        self
+    }
+
+    /// Renders a plaintext preview of the message (content, embeds and button labels).
     ///
-    /// # Watch out!
-    /// This function shall be called only once per one action row. (due to the lack of action row
-    /// identification)
-    fn register_action_row(&mut self) {
-        self.button_count_in_action_row = 0;
-        self.button_count_in_action_row = 0;
+    /// The output is meant for local previews (e.g. in a terminal) and does not resemble the
+    /// payload sent to Discord.
+    pub fn to_plaintext(&self) -> String {
+        let mut lines: Vec<String> = vec![];
+        if let Some(content) = &self.content {
+            lines.push(content.clone());
+        }
+
+        for embed in self.embeds.iter() {
+            lines.push(String::new());
+            lines.push(format!("[Embed] {}", embed.title.as_deref().unwrap_or("")));
+            if let Some(description) = &embed.description {
+                lines.push(description.clone());
+            }
+            for field in embed.fields.iter() {
+                lines.push(format!("{}: {}", field.name, field.value));
+            }
+        }
+
+        for row in self.action_rows.iter() {
+            let labels: Vec<&str> = row
+                .components
+                .iter()
+                .flat_map(|component| match component {
+                    NonCompositeComponent::Button(button) => {
+                        vec![button.label.as_deref().unwrap_or("")]
+                    }
+                    NonCompositeComponent::SelectMenu(menu) => {
+                        menu.options.iter().map(|o| o.label.as_str()).collect()
+                    }
+                })
+                .collect();
+            lines.push(String::new());
+            lines.push(format!("[Components] {}", labels.join(" | ")));
+        }
+
+        lines.join("\n")
     }
 }
 
-#[derive(Serialize, Debug)]
-pub struct Message {
+/// The payload for editing a previously sent webhook message.
+///
+/// Fields set to None are omitted from the payload and thus left unchanged by Discord.
+/// Setting `embeds` or `action_rows` to an empty vector (see `clear_embeds`/`clear_action_rows`)
+/// sends an empty array, which removes all the embeds/components from the message.
+#[derive(Debug)]
+pub struct EditMessage {
     pub content: Option<String>,
-    pub username: Option<String>,
-    pub avatar_url: Option<String>,
-    pub tts: bool,
-    pub embeds: Vec<Embed>,
+    pub embeds: Option<Vec<Embed>>,
     pub allow_mentions: Option<AllowedMentions>,
-    #[serde(rename = "components")]
-    pub action_rows: Vec<ActionRow>,
+    pub action_rows: Option<Vec<ActionRow>>,
+    /// The ids of the existing attachments to keep, the others are removed. None keeps all of
+    /// them (the uploaded files are then sent without their descriptions).
+    pub keep_attachments: Option<Vec<Snowflake>>,
+    /// Files uploaded with the edit
+    pub attachments: Vec<Attachment>,
+    /// The thread of the edited message, sent as the `thread_id` query parameter
+    pub thread_id: Option<Snowflake>,
 }
 
-impl Message {
+impl Serialize for EditMessage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(content) = &self.content {
+            map.serialize_entry("content", content)?;
+        }
+        if let Some(embeds) = &self.embeds {
+            map.serialize_entry("embeds", embeds)?;
+        }
+        if let Some(allow_mentions) = &self.allow_mentions {
+            map.serialize_entry("allowed_mentions", allow_mentions)?;
+        }
+        if let Some(action_rows) = &self.action_rows {
+            map.serialize_entry("components", action_rows)?;
+        }
+        if let Some(keep_attachments) = &self.keep_attachments {
+            // kept attachments are referenced by their id, the uploaded ones by their index
+            let kept = keep_attachments.iter().map(|id| serde_json::json!({ "id": id }));
+            let uploaded = self.attachments.iter().enumerate().map(|(id, attachment)| {
+                serde_json::json!({
+                    "id": id,
+                    "filename": attachment.filename,
+                    "description": attachment.description,
+                })
+            });
+            map.serialize_entry("attachments", &kept.chain(uploaded).collect::<Vec<_>>())?;
+        }
+        map.end()
+    }
+}
+
+impl Default for EditMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditMessage {
     pub fn new() -> Self {
         Self {
             content: None,
-            username: None,
-            avatar_url: None,
-            tts: false,
-            embeds: vec![],
+            embeds: None,
             allow_mentions: None,
-            action_rows: vec![],
+            action_rows: None,
+            keep_attachments: None,
+            attachments: vec![],
+            thread_id: None,
         }
     }
 
-    pub fn content(&mut self, content: &str) -> &mut Self {
-        self.content = Some(content.to_owned());
+    /// Edits a message posted to a thread of the webhook's channel.
+    pub fn thread_id(&mut self, thread_id: &str) -> &mut Self {
+        self.thread_id = Some(thread_id.to_owned());
         self
     }
 
-    pub fn username(&mut self, username: &str) -> &mut Self {
-        self.username = Some(username.to_owned());
+    /// Keeps the existing attachment `attachment_id`, the existing attachments not kept this way
+    /// are removed by the edit.
+    pub fn keep_attachment(&mut self, attachment_id: &str) -> &mut Self {
+        self.keep_attachments.get_or_insert_with(Vec::new).push(attachment_id.to_owned());
         self
     }
 
-    pub fn avatar_url(&mut self, avatar_url: &str) -> &mut Self {
-        self.avatar_url = Some(avatar_url.to_owned());
+    /// Uploads a file with the edit, see `Message::attachment`.
+    pub fn attachment(&mut self, filename: &str, data: Vec<u8>) -> &mut Self {
+        self.attachments.push(Attachment::new(filename, data));
         self
     }
 
-    pub fn tts(&mut self, tts: bool) -> &mut Self {
-        self.tts = tts;
+    pub fn content(&mut self, content: &str) -> &mut Self {
+        self.content = Some(content.to_owned());
         self
     }
 
+    /// Adds an embed, the embeds of the edited message are replaced by the embeds added
+    /// to the edit.
     pub fn embed<Func>(&mut self, func: Func) -> &mut Self
     where
         Func: Fn(&mut Embed) -> &mut Embed,
     {
         let mut embed = Embed::new();
         func(&mut embed);
-        self.embeds.push(embed);
+        self.embeds.get_or_insert_with(Vec::new).push(embed);
+
+        self
+    }
+
+    /// Adds an already built embed, see `embed`.
+    pub fn add_embed(&mut self, embed: Embed) -> &mut Self {
+        self.embeds.get_or_insert_with(Vec::new).push(embed);
+        self
+    }
 
+    /// Removes all embeds from the edited message.
+    pub fn clear_embeds(&mut self) -> &mut Self {
+        self.embeds = Some(vec![]);
         self
     }
 
+    /// Adds an action row, the components of the edited message are replaced by the action rows
+    /// added to the edit.
     pub fn action_row<Func>(&mut self, func: Func) -> &mut Self
     where
         Func: Fn(&mut ActionRow) -> &mut ActionRow,
     {
         let mut row = ActionRow::new();
         func(&mut row);
-        self.action_rows.push(row);
+        self.action_rows.get_or_insert_with(Vec::new).push(row);
 
         self
     }
 
-    interval_member!(ACTION_ROW_COUNT_INTERVAL, usize, 0, 5);
-    interval_member!(LABEL_LEN_INTERVAL, usize, 0, 80);
-    interval_member!(CUSTOM_ID_LEN_INTERVAL, usize, 1, 100);
-    // Additionally, the combined sum of characters in all title, description, field.name,
-    // field.value, footer.text, and author.name fields across all embeds attached to a message
-    // must not exceed 6000 characters.
-    interval_member!(EMBED_TOTAL_TEXT_LEN_INTERVAL, usize, 0, 6000);
+    /// Adds an already built action row, see `action_row`.
+    pub fn add_action_row(&mut self, row: ActionRow) -> &mut Self {
+        self.action_rows.get_or_insert_with(Vec::new).push(row);
+        self
+    }
+
+    /// Removes all components from the edited message.
+    pub fn clear_action_rows(&mut self) -> &mut Self {
+        self.action_rows = Some(vec![]);
+        self
+    }
 
     pub fn allow_mentions(
         &mut self,
@@ -235,7 +1537,7 @@ impl Message {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct Embed {
     pub title: Option<String>,
     #[serde(rename = "type")]
@@ -254,6 +1556,12 @@ pub struct Embed {
     pub fields: Vec<EmbedField>,
 }
 
+impl Default for Embed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Embed {
     pub fn new() -> Self {
         Self {
@@ -273,6 +1581,40 @@ impl Embed {
         }
     }
 
+    /// The number of characters counted towards `Message::EMBED_TOTAL_TEXT_LEN_INTERVAL`.
+    pub(crate) fn character_count(&self) -> usize {
+        self.title.as_ref().map_or(0, |s| s.len())
+            + self.description.as_ref().map_or(0, |s| s.len())
+            + self.footer.as_ref().map_or(0, |f| f.text.len())
+            + self.author.as_ref().map_or(0, |a| a.name.len())
+            + self.fields.iter().map(|f| f.name.len() + f.value.len()).sum::<usize>()
+    }
+
+    /// The filenames of the uploaded files the embed refers to (`attachment://{filename}`).
+    pub(crate) fn attachment_references(&self) -> impl Iterator<Item = &str> {
+        const ATTACHMENT_SCHEME: &str = "attachment://";
+        vec![
+            self.image.as_ref().map(|i| &i.url),
+            self.thumbnail.as_ref().map(|t| &t.url),
+            self.video.as_ref().map(|v| &v.url),
+            self.footer.as_ref().and_then(|f| f.icon_url.as_ref()),
+            self.author.as_ref().and_then(|a| a.icon_url.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|url| url.strip_prefix(ATTACHMENT_SCHEME))
+    }
+
+    /// Builds a standalone embed, which can be reused across messages.
+    pub fn build<Func>(func: Func) -> Embed
+    where
+        Func: FnOnce(&mut Embed) -> &mut Embed,
+    {
+        let mut embed = Embed::new();
+        func(&mut embed);
+        embed
+    }
+
     pub fn title(&mut self, title: &str) -> &mut Self {
         self.title = Some(title.to_owned());
         self
@@ -293,6 +1635,13 @@ impl Embed {
         self
     }
 
+    /// Sets the timestamp, serialized as RFC 3339 (ISO8601).
+    #[cfg(feature = "chrono")]
+    pub fn timestamp_dt(&mut self, timestamp: chrono::DateTime<chrono::Utc>) -> &mut Self {
+        self.timestamp = Some(timestamp.to_rfc3339());
+        self
+    }
+
     pub fn color(&mut self, color: &str) -> &mut Self {
         self.color = Some(color.to_owned());
         self
@@ -303,6 +1652,18 @@ impl Embed {
         self
     }
 
+    /// Same as `footer`, but the footer is validated right away, e.g. to catch text passed
+    /// as the icon URL early.
+    ///
+    /// # Return value
+    /// Error variant contains an error message, the footer is not set then
+    pub fn try_footer(&mut self, text: &str, icon_url: Option<String>) -> Result<&mut Self, ValidationError> {
+        let footer = EmbedFooter::new(text, icon_url);
+        footer.check_compatibility(&mut MessageContext::new())?;
+        self.footer = Some(footer);
+        Ok(self)
+    }
+
     pub fn image(&mut self, url: &str) -> &mut Self {
         self.image = Some(EmbedImage::new(url));
         self
@@ -333,22 +1694,83 @@ impl Embed {
         self
     }
 
+    /// Same as `author`, but the author is validated right away, see `try_footer`.
+    ///
+    /// # Return value
+    /// Error variant contains an error message, the author is not set then
+    pub fn try_author(
+        &mut self,
+        name: &str,
+        url: Option<String>,
+        icon_url: Option<String>,
+    ) -> Result<&mut Self, ValidationError> {
+        let author = EmbedAuthor::new(name, url, icon_url);
+        author.check_compatibility(&mut MessageContext::new())?;
+        self.author = Some(author);
+        Ok(self)
+    }
+
     pub fn field(&mut self, name: &str, value: &str, inline: bool) -> &mut Self {
-        if self.fields.len() == Embed::FIELDS_LEN_INTERVAL.max_allowed {
-            panic!("You can't have more than {} fields in an embed!", Embed::FIELDS_LEN_INTERVAL.max_allowed)
+        self.add_field(EmbedField::new(name, value, inline))
+    }
+
+    /// Adds a field with a value of any length, a value too long for a single field is split
+    /// into multiple fields named `name`, `name (cont.)`, ... Values are split on line
+    /// boundaries where possible.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (too many fields), no field is added then
+    pub fn field_chunked(&mut self, name: &str, value: &str, inline: bool) -> Result<&mut Self, ValidationError> {
+        let chunks = split_chunks(value, EmbedField::VALUE_LEN_INTERVAL.max_allowed);
+        interval_check(
+            &Embed::FIELDS_LEN_INTERVAL,
+            &(self.fields.len() + chunks.len()),
+            "Embed field count",
+        )?;
+        let continued_name = format!("{} (cont.)", name);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let name = if index == 0 { name } else { &continued_name };
+            self.fields.push(EmbedField::new(name, chunk, inline));
         }
+        Ok(self)
+    }
 
-        self.fields.push(EmbedField::new(name, value, inline));
+    /// Adds an already built field, see `field`.
+    pub fn add_field(&mut self, field: EmbedField) -> &mut Self {
+        self.fields.push(field);
         self
     }
 
     interval_member!(TITLE_LEN_INTERVAL, usize, 0, 256);
     interval_member!(DESCRIPTION_LEN_INTERVAL, usize, 0, 4096);
-    // enforced in field... by panic though... todo!
     interval_member!(FIELDS_LEN_INTERVAL, usize, 0, 25);
 }
 
-#[derive(Serialize, Debug)]
+/// Splits `text` into chunks of at most `max_len` bytes, preferably at (and without) newlines.
+fn split_chunks(text: &str, max_len: usize) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = text;
+    while rest.len() > max_len {
+        let mut end = max_len;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        match rest[..end].rfind('\n') {
+            Some(newline) if newline > 0 => {
+                chunks.push(&rest[..newline]);
+                rest = &rest[newline + 1..];
+            }
+            _ => {
+                chunks.push(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+    }
+    chunks.push(rest);
+    chunks
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct EmbedField {
     pub name: String,
     pub value: String,
@@ -367,7 +1789,7 @@ impl EmbedField {
     interval_member!(VALUE_LEN_INTERVAL, usize, 0, 1024);
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct EmbedFooter {
     pub text: String,
     pub icon_url: Option<String>,
@@ -387,7 +1809,7 @@ pub type EmbedImage = EmbedUrlSource;
 pub type EmbedThumbnail = EmbedUrlSource;
 pub type EmbedVideo = EmbedUrlSource;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct EmbedUrlSource {
     pub url: String,
 }
@@ -400,7 +1822,7 @@ impl EmbedUrlSource {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct EmbedProvider {
     pub name: String,
     pub url: String,
@@ -415,7 +1837,7 @@ impl EmbedProvider {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct EmbedAuthor {
     pub name: String,
     pub url: Option<String>,
@@ -447,43 +1869,72 @@ fn resolve_allowed_mention_name(allowed_mention: AllowedMention) -> String {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct AllowedMentions {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parse: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub roles: Option<Vec<Snowflake>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub users: Option<Vec<Snowflake>>,
     pub replied_user: bool,
 }
 
 impl AllowedMentions {
+    /// Creates the allowed mentions, the `parse` field is omitted if `parse` is None
+    /// (unlike an empty `parse`, which suppresses the mentions not listed in `roles`/`users`).
     pub fn new(
         parse: Option<Vec<AllowedMention>>,
         roles: Option<Vec<Snowflake>>,
         users: Option<Vec<Snowflake>>,
         replied_user: bool,
     ) -> Self {
-        let mut parse_strings: Vec<String> = vec![];
-        if parse.is_some() {
-            parse
-                .unwrap()
-                .into_iter()
-                .for_each(|x| parse_strings.push(resolve_allowed_mention_name(x)))
-        }
+        let parse = parse.map(|parse| parse.into_iter().map(resolve_allowed_mention_name).collect());
 
         Self {
-            parse: Some(parse_strings),
+            parse,
             roles,
             users,
             replied_user,
         }
     }
+
+    /// Suppresses all mentions, nobody is pinged.
+    pub fn none() -> Self {
+        AllowedMentions::new(Some(vec![]), None, None, false)
+    }
+
+    /// Allows all mentions (roles, users and `@everyone`/`@here`) found in the content.
+    pub fn all() -> Self {
+        AllowedMentions::new(
+            Some(vec![
+                AllowedMention::RoleMention,
+                AllowedMention::UserMention,
+                AllowedMention::EveryoneMention,
+            ]),
+            None,
+            None,
+            false,
+        )
+    }
+
+    /// Allows pinging only the `users`, all the other mentions are suppressed.
+    pub fn only_users(users: Vec<Snowflake>) -> Self {
+        AllowedMentions::new(Some(vec![]), None, Some(users), false)
+    }
+
+    /// The mention types as serialized in the `parse` array, empty if `parse` is not set.
+    pub fn parse_values(&self) -> &[String] {
+        self.parse.as_deref().unwrap_or(&[])
+    }
 }
 
 // ready to be extended with other components
 // non-composite here specifically means *not an action row*
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 enum NonCompositeComponent {
     Button(Button),
+    SelectMenu(SelectMenu),
 }
 
 impl Serialize for NonCompositeComponent {
@@ -493,19 +1944,43 @@ impl Serialize for NonCompositeComponent {
     {
         match self {
             NonCompositeComponent::Button(button) => button.serialize(serializer),
+            NonCompositeComponent::SelectMenu(menu) => menu.serialize(serializer),
         }
     }
 }
 
-#[derive(Serialize, Debug)]
+impl<'de> Deserialize<'de> for NonCompositeComponent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match value.get("type").and_then(serde_json::Value::as_u64) {
+            Some(2) => serde_json::from_value(value).map(NonCompositeComponent::Button).map_err(D::Error::custom),
+            Some(3) => serde_json::from_value(value).map(NonCompositeComponent::SelectMenu).map_err(D::Error::custom),
+            Some(other) => Err(D::Error::custom(format!("Unknown component type ({})", other))),
+            None => Err(D::Error::custom("Component type is missing")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ActionRow {
     #[serde(rename = "type")]
     pub component_type: u8,
     components: Vec<NonCompositeComponent>,
 }
 
+impl Default for ActionRow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ActionRow {
-    fn new() -> ActionRow {
+    pub fn new() -> ActionRow {
         ActionRow {
             component_type: 1,
             components: vec![],
@@ -518,6 +1993,11 @@ impl ActionRow {
     {
         let mut button = LinkButton::new();
         button_mutator(&mut button);
+        self.add_link_button(button)
+    }
+
+    /// Adds an already built link button, see `link_button`.
+    pub fn add_link_button(&mut self, button: LinkButton) -> &mut Self {
         self.components.push(NonCompositeComponent::Button(
             button.to_serializable_button(),
         ));
@@ -530,11 +2010,90 @@ impl ActionRow {
     {
         let mut button = RegularButton::new();
         button_mutator(&mut button);
+        self.add_regular_button(button)
+    }
+
+    /// Adds an already built regular button, see `regular_button`.
+    pub fn add_regular_button(&mut self, button: RegularButton) -> &mut Self {
         self.components.push(NonCompositeComponent::Button(
             button.to_serializable_button(),
         ));
         self
     }
+
+    /// Creates an action row with a success "Confirm" and a danger "Cancel" button.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (invalid or identical custom ids)
+    pub fn confirm_cancel(confirm_id: &str, cancel_id: &str) -> Result<ActionRow, ValidationError> {
+        let mut context = MessageContext::with_limit_profile(LimitProfile::default());
+        context.register_custom_id(confirm_id)?;
+        context.register_custom_id(cancel_id)?;
+
+        let mut row = ActionRow::new();
+        row.regular_button(|button| {
+            button
+                .style(NonLinkButtonStyle::Success)
+                .label("Confirm")
+                .custom_id(confirm_id)
+        })
+        .regular_button(|button| {
+            button
+                .style(NonLinkButtonStyle::Danger)
+                .label("Cancel")
+                .custom_id(cancel_id)
+        });
+        Ok(row)
+    }
+
+    /// Adds a (string) select menu. A select menu must be the only component of its action row.
+    pub fn select_menu<Func>(&mut self, menu_mutator: Func) -> &mut Self
+    where
+        Func: Fn(&mut SelectMenu) -> &mut SelectMenu,
+    {
+        let mut menu = SelectMenu::new();
+        menu_mutator(&mut menu);
+        self.add_select_menu(menu)
+    }
+
+    /// The labels of the buttons in the row.
+    fn button_labels(&self) -> impl Iterator<Item = &str> {
+        self.components.iter().filter_map(|component| match component {
+            NonCompositeComponent::Button(button) => button.label.as_deref(),
+            NonCompositeComponent::SelectMenu(_) => None,
+        })
+    }
+
+    /// Adds an already built select menu, see `select_menu`.
+    pub fn add_select_menu(&mut self, menu: SelectMenu) -> &mut Self {
+        self.components.push(NonCompositeComponent::SelectMenu(menu));
+        self
+    }
+
+    /// Reads back an action row from its JSON representation (e.g. a stored component) and
+    /// validates it.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (malformed JSON, unknown component type or
+    /// failed validation)
+    pub fn from_json(json: &str) -> Result<ActionRow, ValidationError> {
+        let row: ActionRow = serde_json::from_str(json)
+            .map_err(|e| ValidationError::Other(format!("Invalid action row JSON: {}", e)))?;
+        let mut context = MessageContext::with_limit_profile(LimitProfile::default());
+        row.check_deserialized(&mut context)?;
+        Ok(row)
+    }
+
+    /// Checks a deserialized row, including the row's own component type.
+    fn check_deserialized(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        if self.component_type != 1 {
+            return Err(ValidationError::Other(format!(
+                "Unknown component type ({}), expected an action row (1)!",
+                self.component_type
+            )));
+        }
+        self.check_compatibility(context)
+    }
     interval_member!(BUTTON_COUNT_INTERVAL, usize, 0, 5);
 }
 
@@ -546,6 +2105,24 @@ pub enum NonLinkButtonStyle {
     Danger,
 }
 
+impl std::str::FromStr for NonLinkButtonStyle {
+    type Err = ValidationError;
+
+    /// Parses the style name ("primary", "secondary", "success" or "danger"), ignoring case.
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        match style.to_lowercase().as_str() {
+            "primary" => Ok(NonLinkButtonStyle::Primary),
+            "secondary" => Ok(NonLinkButtonStyle::Secondary),
+            "success" => Ok(NonLinkButtonStyle::Success),
+            "danger" => Ok(NonLinkButtonStyle::Danger),
+            _ => Err(ValidationError::Other(format!(
+                "Unknown button style ({}), expected primary, secondary, success or danger!",
+                style
+            ))),
+        }
+    }
+}
+
 impl NonLinkButtonStyle {
     fn get_button_style(&self) -> ButtonStyles {
         match *self {
@@ -560,7 +2137,7 @@ impl NonLinkButtonStyle {
 // since link button has an explicit way of creation via the action row
 // this enum is kept hidden from the user ans the NonLinkButtonStyle is created to avoid
 // user confusion
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 enum ButtonStyles {
     Primary,
     Secondary,
@@ -585,7 +2162,134 @@ impl Serialize for ButtonStyles {
     }
 }
 
-#[derive(Serialize, Debug, Clone)]
+impl<'de> Deserialize<'de> for ButtonStyles {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match i32::deserialize(deserializer)? {
+            1 => Ok(ButtonStyles::Primary),
+            2 => Ok(ButtonStyles::Secondary),
+            3 => Ok(ButtonStyles::Success),
+            4 => Ok(ButtonStyles::Danger),
+            5 => Ok(ButtonStyles::Link),
+            other => Err(serde::de::Error::custom(format!("Unknown button style ({})", other))),
+        }
+    }
+}
+
+/// A file uploaded with a message.
+#[derive(Debug)]
+pub struct Attachment {
+    pub filename: String,
+    pub description: Option<String>,
+    pub data: AttachmentData,
+}
+
+impl Attachment {
+    pub fn new(filename: &str, data: Vec<u8>) -> Self {
+        Self {
+            filename: filename.to_owned(),
+            description: None,
+            data: AttachmentData::Bytes(data),
+        }
+    }
+
+    /// Creates an attachment whose content is streamed from `reader` while the request is sent,
+    /// instead of being buffered in memory. `len` is the size of the content (in bytes),
+    /// used to validate the upload limits.
+    ///
+    /// The reader is consumed by sending, a message with a streamed attachment can be sent only
+    /// once.
+    #[cfg(any(feature = "client", feature = "rustls"))]
+    pub fn from_reader<R>(filename: &str, reader: R, len: usize) -> Self
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        Self {
+            filename: filename.to_owned(),
+            description: None,
+            data: AttachmentData::Stream(AttachmentStream {
+                reader: Mutex::new(Some(Box::pin(reader))),
+                len,
+            }),
+        }
+    }
+
+    pub fn description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_owned());
+        self
+    }
+}
+
+/// The content of an `Attachment`.
+#[derive(Debug)]
+pub enum AttachmentData {
+    Bytes(Vec<u8>),
+    #[cfg(any(feature = "client", feature = "rustls"))]
+    Stream(AttachmentStream),
+}
+
+impl AttachmentData {
+    /// The size of the content in bytes (the declared size for streamed content).
+    pub fn len(&self) -> usize {
+        match self {
+            AttachmentData::Bytes(data) => data.len(),
+            #[cfg(any(feature = "client", feature = "rustls"))]
+            AttachmentData::Stream(stream) => stream.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Streamed attachment content, see `Attachment::from_reader`.
+#[cfg(any(feature = "client", feature = "rustls"))]
+pub struct AttachmentStream {
+    reader: Mutex<Option<Pin<Box<dyn AsyncRead + Send>>>>,
+    len: usize,
+}
+
+#[cfg(any(feature = "client", feature = "rustls"))]
+impl AttachmentStream {
+    /// Takes the reader out of the stream, None if it has already been taken (sent).
+    pub(crate) fn take_reader(&self) -> Option<Pin<Box<dyn AsyncRead + Send>>> {
+        self.reader.lock().ok().and_then(|mut reader| reader.take())
+    }
+}
+
+#[cfg(any(feature = "client", feature = "rustls"))]
+impl std::fmt::Debug for AttachmentStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachmentStream").field("len", &self.len).finish()
+    }
+}
+
+/// The metadata of an attachment in the JSON payload, the `id` refers to the `files[{id}]`
+/// part of the multipart request.
+#[derive(Serialize)]
+struct AttachmentMetadata<'a> {
+    id: usize,
+    filename: &'a str,
+    description: Option<&'a str>,
+}
+
+fn serialize_attachments<S>(attachments: &[Attachment], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(attachments.iter().enumerate().map(|(id, attachment)| {
+        AttachmentMetadata {
+            id,
+            filename: &attachment.filename,
+            description: attachment.description.as_deref(),
+        }
+    }))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PartialEmoji {
     pub id: Snowflake,
     pub name: String,
@@ -593,7 +2297,7 @@ pub struct PartialEmoji {
 }
 
 /// the button struct intended for serialized
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 struct Button {
     #[serde(rename = "type")]
     pub component_type: i8,
@@ -624,6 +2328,8 @@ impl Button {
             disabled,
         }
     }
+
+    interval_member!(URL_LEN_INTERVAL, usize, 1, 512);
 }
 
 /// Data holder for shared fields of link and regular buttons
@@ -689,8 +2395,14 @@ pub struct LinkButton {
     url: Option<String>,
 }
 
+impl Default for LinkButton {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LinkButton {
-    fn new() -> Self {
+    pub fn new() -> Self {
         LinkButton {
             button_base: ButtonCommonBase::new(None, None, None),
             url: None,
@@ -711,8 +2423,14 @@ pub struct RegularButton {
     style: Option<NonLinkButtonStyle>,
 }
 
+impl Default for RegularButton {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RegularButton {
-    fn new() -> Self {
+    pub fn new() -> Self {
         RegularButton {
             button_base: ButtonCommonBase::new(None, None, None),
             custom_id: None,
@@ -730,9 +2448,101 @@ impl RegularButton {
         self
     }
 
+    /// Sets the style by its name, e.g. from a configuration file, see `NonLinkButtonStyle::from_str`.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (unknown style)
+    pub fn style_from_str(&mut self, style: &str) -> Result<&mut Self, ValidationError> {
+        Ok(self.style(style.parse()?))
+    }
+
     button_base_delegation!(button_base);
 }
 
+/// Helper for custom ids carrying a structured payload as colon-delimited segments
+/// (e.g. `vote:yes:msg123`), useful for routing interactions.
+///
+/// # Example
+/// ```
+/// # use webhook::models::CustomId;
+/// let id = CustomId::new("vote").arg("yes").arg("msg123").build().unwrap();
+/// assert_eq!(id, "vote:yes:msg123");
+///
+/// let parsed = CustomId::parse(&id).unwrap();
+/// assert_eq!(parsed.prefix(), "vote");
+/// assert_eq!(parsed.args(), ["yes", "msg123"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomId {
+    prefix: String,
+    args: Vec<String>,
+}
+
+impl CustomId {
+    pub const SEPARATOR: char = ':';
+
+    pub fn new(prefix: &str) -> Self {
+        CustomId {
+            prefix: prefix.to_string(),
+            args: vec![],
+        }
+    }
+
+    pub fn arg(&mut self, arg: &str) -> &mut Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Joins the segments into a custom id.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (a segment contains the separator, the result
+    /// does not fit Discord's custom id length limit)
+    pub fn build(&self) -> Result<String, ValidationError> {
+        if let Some(segment) = std::iter::once(&self.prefix)
+            .chain(self.args.iter())
+            .find(|segment| segment.contains(Self::SEPARATOR))
+        {
+            return Err(ValidationError::Other(format!(
+                "Custom ID segment ({}) must not contain the '{}' separator!",
+                segment,
+                Self::SEPARATOR
+            )));
+        }
+
+        let mut custom_id = self.prefix.clone();
+        for arg in self.args.iter() {
+            custom_id.push(Self::SEPARATOR);
+            custom_id.push_str(arg);
+        }
+        interval_check(&Message::CUSTOM_ID_LEN_INTERVAL, &custom_id.len(), "Custom ID length")?;
+        Ok(custom_id)
+    }
+
+    /// Splits a custom id into the prefix and its arguments.
+    ///
+    /// # Return value
+    /// Error variant contains an error message (the custom id does not fit Discord's custom id
+    /// length limit)
+    pub fn parse(custom_id: &str) -> Result<CustomId, ValidationError> {
+        interval_check(&Message::CUSTOM_ID_LEN_INTERVAL, &custom_id.len(), "Custom ID length")?;
+        let mut segments = custom_id.split(Self::SEPARATOR).map(|s| s.to_string());
+        Ok(CustomId {
+            // split always yields at least one segment
+            prefix: segments.next().unwrap_or_default(),
+            args: segments.collect(),
+        })
+    }
+}
+
 trait ToSerializableButton {
     fn to_serializable_button(&self) -> Button;
 }
@@ -763,124 +2573,502 @@ impl ToSerializableButton for RegularButton {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SelectMenu {
+    #[serde(rename = "type")]
+    pub component_type: u8,
+    custom_id: Option<String>,
+    #[serde(default)]
+    options: Vec<SelectOption>,
+    placeholder: Option<String>,
+    min_values: Option<u8>,
+    max_values: Option<u8>,
+    disabled: Option<bool>,
+}
+
+impl Default for SelectMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelectMenu {
+    pub fn new() -> Self {
+        SelectMenu {
+            component_type: 3,
+            custom_id: None,
+            options: vec![],
+            placeholder: None,
+            min_values: None,
+            max_values: None,
+            disabled: None,
+        }
+    }
+
+    /// Builds a standalone select menu, see `ActionRow::add_select_menu`.
+    pub fn build<Func>(func: Func) -> SelectMenu
+    where
+        Func: FnOnce(&mut SelectMenu) -> &mut SelectMenu,
+    {
+        let mut menu = SelectMenu::new();
+        func(&mut menu);
+        menu
+    }
+
+    pub fn custom_id(&mut self, custom_id: &str) -> &mut Self {
+        self.custom_id = Some(custom_id.to_string());
+        self
+    }
+
+    pub fn placeholder(&mut self, placeholder: &str) -> &mut Self {
+        self.placeholder = Some(placeholder.to_string());
+        self
+    }
+
+    /// Sets the minimum number of options that must be chosen (Discord's default is 1).
+    pub fn min_values(&mut self, min_values: u8) -> &mut Self {
+        self.min_values = Some(min_values);
+        self
+    }
+
+    /// Sets the maximum number of options that can be chosen (Discord's default is 1).
+    pub fn max_values(&mut self, max_values: u8) -> &mut Self {
+        self.max_values = Some(max_values);
+        self
+    }
+
+    pub fn disabled(&mut self, disabled: bool) -> &mut Self {
+        self.disabled = Some(disabled);
+        self
+    }
+
+    pub fn option<Func>(&mut self, option_mutator: Func) -> &mut Self
+    where
+        Func: Fn(&mut SelectOption) -> &mut SelectOption,
+    {
+        let mut option = SelectOption::new();
+        option_mutator(&mut option);
+        self.add_option(option)
+    }
+
+    /// Adds an option per `(label, value)` pair. The option count and the lengths are
+    /// validated with the rest of the message.
+    pub fn options_from(&mut self, items: &[(&str, &str)]) -> &mut Self {
+        for (label, value) in items {
+            self.option(|option| option.label(label).value(value));
+        }
+        self
+    }
+
+    /// Adds an already built option, see `option`.
+    pub fn add_option(&mut self, option: SelectOption) -> &mut Self {
+        self.options.push(option);
+        self
+    }
+
+    interval_member!(OPTION_COUNT_INTERVAL, usize, 1, 25);
+    interval_member!(PLACEHOLDER_LEN_INTERVAL, usize, 0, 150);
+    interval_member!(MIN_VALUES_INTERVAL, u8, 0, 25);
+    interval_member!(MAX_VALUES_INTERVAL, u8, 1, 25);
+    // the values Discord uses when min_values/max_values are omitted
+    const DEFAULT_MIN_VALUES: u8 = 1;
+    const DEFAULT_MAX_VALUES: u8 = 1;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SelectOption {
+    label: String,
+    value: String,
+    description: Option<String>,
+    emoji: Option<PartialEmoji>,
+    default: Option<bool>,
+}
+
+impl SelectOption {
+    // no Default impl, `default` is the setter of the option's default flag
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        SelectOption {
+            label: String::new(),
+            value: String::new(),
+            description: None,
+            emoji: None,
+            default: None,
+        }
+    }
+
+    pub fn label(&mut self, label: &str) -> &mut Self {
+        self.label = label.to_string();
+        self
+    }
+
+    pub fn value(&mut self, value: &str) -> &mut Self {
+        self.value = value.to_string();
+        self
+    }
+
+    pub fn description(&mut self, description: &str) -> &mut Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn emoji(&mut self, emoji_id: &str, name: &str, animated: bool) -> &mut Self {
+        self.emoji = Some(PartialEmoji {
+            id: emoji_id.to_string(),
+            name: name.to_string(),
+            animated: Some(animated),
+        });
+        self
+    }
+
+    /// Marks the option as selected by default.
+    pub fn default(&mut self, default: bool) -> &mut Self {
+        self.default = Some(default);
+        self
+    }
+
+    interval_member!(LABEL_LEN_INTERVAL, usize, 1, 100);
+    interval_member!(VALUE_LEN_INTERVAL, usize, 1, 100);
+    interval_member!(DESCRIPTION_LEN_INTERVAL, usize, 0, 100);
+}
+
 /// A trait for checking that an API message component is compatible with the official Discord API constraints
 ///
 /// This trait should be implemented for any components for which the Discord API documentation states
 /// limitations (maximum count, maximum length, uniqueness with respect to other components, restrictions
 /// on children components, ...)
 pub(crate) trait DiscordApiCompatible {
-    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), String>;
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError>;
 }
 
 impl DiscordApiCompatible for NonCompositeComponent {
-    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), String> {
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
         match self {
             NonCompositeComponent::Button(b) => b.check_compatibility(context),
+            NonCompositeComponent::SelectMenu(m) => m.check_compatibility(context),
         }
     }
 }
 
 impl DiscordApiCompatible for Button {
-    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), String> {
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
         if let Some(label) = &self.label {
             interval_check(&Message::LABEL_LEN_INTERVAL, &label.len(), "Label length")?;
         }
+        if let Some(emoji) = &self.emoji {
+            context.register_emoji(emoji)?;
+        }
 
-        return match self.style {
-            None => Err("Button style must be set!".to_string()),
-            Some(ButtonStyles::Link) => {
-                if self.url.is_none() {
-                    Err("Url of a Link button must be set!".to_string())
-                } else {
-                    Ok(())
-                }
-            }
+        match self.style {
+            None => Err(ValidationError::Other("Button style must be set!".to_string())),
+            Some(ButtonStyles::Link) => match self.url.as_ref() {
+                None => Err(ValidationError::Other("Url of a Link button must be set!".to_string())),
+                Some(url) => interval_check(&Button::URL_LEN_INTERVAL, &url.len(), "Link button url length"),
+            },
             // list all remaining in case a style with different requirements is added
             Some(ButtonStyles::Danger)
             | Some(ButtonStyles::Primary)
             | Some(ButtonStyles::Success)
             | Some(ButtonStyles::Secondary) => {
-                return if let Some(id) = self.custom_id.as_ref() {
+                if let Some(id) = self.custom_id.as_ref() {
                     context.register_button(id)
                 } else {
-                    Err("Custom ID of a NonLink button must be set!".to_string())
-                };
+                    Err(ValidationError::Other("Custom ID of a NonLink button must be set!".to_string()))
+                }
             }
-        };
+        }
+    }
+}
+
+impl DiscordApiCompatible for SelectMenu {
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        match self.custom_id.as_ref() {
+            Some(id) => context.register_select_menu(id)?,
+            None => return Err(ValidationError::Other("Custom ID of a select menu must be set!".to_string())),
+        }
+
+        interval_check(
+            &Self::OPTION_COUNT_INTERVAL,
+            &self.options.len(),
+            "Select menu option count")?;
+
+        if let Some(placeholder) = self.placeholder.as_ref() {
+            interval_check(
+                &Self::PLACEHOLDER_LEN_INTERVAL,
+                &placeholder.len(),
+                "Select menu placeholder length")?;
+        }
+
+        if let Some(min_values) = self.min_values.as_ref() {
+            interval_check(&Self::MIN_VALUES_INTERVAL, min_values, "Select menu min values")?;
+        }
+
+        if let Some(max_values) = self.max_values.as_ref() {
+            interval_check(&Self::MAX_VALUES_INTERVAL, max_values, "Select menu max values")?;
+        }
+
+        // an omitted value is substituted by Discord's default, so the bounds have to be
+        // consistent even when only one of them is set
+        let min_values = self.min_values.unwrap_or(Self::DEFAULT_MIN_VALUES);
+        let max_values = self.max_values.unwrap_or(Self::DEFAULT_MAX_VALUES);
+        if self.max_values.is_none() && min_values > max_values {
+            return Err(ValidationError::Other(format!(
+                "Select menu min values ({}) require setting max values, without max values \
+                the select menu is single-select!",
+                min_values
+            )));
+        }
+        if min_values > max_values {
+            return Err(ValidationError::Other(format!(
+                "Select menu min values ({}) greater than max values ({})!",
+                min_values, max_values
+            )));
+        }
+
+        // checked per menu first, so that the error names the menu
+        let mut emoji_animated_flags: HashMap<&str, bool> = HashMap::new();
+        for emoji in self.options.iter().filter_map(|option| option.emoji.as_ref()) {
+            let animated = match emoji.animated {
+                Some(animated) => animated,
+                None => continue,
+            };
+            match emoji_animated_flags.insert(&emoji.id, animated) {
+                Some(registered) if registered != animated => {
+                    return Err(ValidationError::Other(format!(
+                        "Emoji ({}) used with conflicting animated flags in the options of select menu ({})!",
+                        emoji.id,
+                        self.custom_id.as_deref().unwrap_or_default()
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        for option in self.options.iter() {
+            option.check_compatibility(context)?;
+        }
+        Ok(())
+    }
+}
+
+impl DiscordApiCompatible for SelectOption {
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        interval_check(&Self::LABEL_LEN_INTERVAL, &self.label.len(), "Select option label length")?;
+        interval_check(&Self::VALUE_LEN_INTERVAL, &self.value.len(), "Select option value length")?;
+        if let Some(description) = self.description.as_ref() {
+            interval_check(
+                &Self::DESCRIPTION_LEN_INTERVAL,
+                &description.len(),
+                "Select option description length")?;
+        }
+        if let Some(emoji) = &self.emoji {
+            context.register_emoji(emoji)?;
+        }
+        Ok(())
     }
 }
 
 impl DiscordApiCompatible for ActionRow {
-    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), String> {
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
         context.register_action_row();
         if self.components.is_empty() {
-            return Err("Empty action row detected!".to_string());
+            return Err(ValidationError::Other("Empty action row detected!".to_string()));
         }
 
-        self.components.iter().fold(Ok(()), |acc, component| {
-            acc.and(component.check_compatibility(context))
-        })
+        for component in self.components.iter() {
+            let result = component.check_compatibility(context);
+            context.report(result)?;
+        }
+        Ok(())
     }
 }
 
 impl DiscordApiCompatible for Message {
-    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), String> {
-        interval_check(
-            &Message::ACTION_ROW_COUNT_INTERVAL,
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        let result = interval_check(
+            &context.limits.embed_count,
+            &self.embeds.len(),
+            "Embed count");
+        context.report(result)?;
+        if self.has_flag(Message::FLAG_IS_COMPONENTS_V2) && (self.content.is_some() || !self.embeds.is_empty()) {
+            context.report(Err(ValidationError::Other(
+                "A components v2 message (IS_COMPONENTS_V2 flag) cannot set content or embeds!".to_string()
+            )))?;
+        }
+        if self.webhook_kind == Some(WebhookKind::Incoming) && !self.action_rows.is_empty() {
+            context.report(Err(ValidationError::Other(
+                "An incoming webhook message cannot have components, they are supported only by application webhooks!"
+                    .to_string()
+            )))?;
+        }
+        if self.has_flag(Message::FLAG_EPHEMERAL) && context.target != ValidationTarget::InteractionFollowup {
+            context.report(Err(ValidationError::Other(
+                "The EPHEMERAL flag is allowed only for interaction followup messages!".to_string()
+            )))?;
+        }
+        if let Some(content) = self.content.as_ref() {
+            let result = interval_check(
+                &context.limits.content_len,
+                &content.len(),
+                "Content length");
+            context.report(result)?;
+        }
+        let result = interval_check(
+            &context.limits.action_row_count,
             &self.action_rows.len(),
-            "Action row count")?;
+            "Action row count");
+        context.report(result)?;
+        let result = context.register_attachments(&self.attachments);
+        context.report(result)?;
+        if let Some(allow_mentions) = self.allow_mentions.as_ref() {
+            let result = allow_mentions.check_compatibility(context);
+            context.report(result)?;
+            // mentioning the replied user only makes sense for a reply
+            if allow_mentions.replied_user && self.message_reference.is_none() {
+                context.report(Err(ValidationError::Other(
+                    "Allowed mentions cannot mention the replied user (replied_user), the message has no message reference (it is not a reply)!".to_string()
+                )))?;
+            }
+        }
 
-        self.embeds
-            .iter()
-            .fold(Ok(()), |acc, emb| acc.and(emb.check_compatibility(context)))?;
+        for embed in self.embeds.iter() {
+            let result = embed.check_compatibility(context);
+            context.report(result)?;
+        }
 
-        self.action_rows
-            .iter()
-            .fold(Ok(()), |acc, row| acc.and(row.check_compatibility(context)))
+        for (index, embed) in self.embeds.iter().enumerate() {
+            for filename in embed.attachment_references() {
+                if !self.attachments.iter().any(|a| a.filename == filename) {
+                    context.report(Err(ValidationError::Other(format!(
+                        "Embed {} references the attachment ({}) which is not uploaded with the message!",
+                        index, filename
+                    ))))?;
+                }
+            }
+        }
+
+        for row in self.action_rows.iter() {
+            let result = row.check_compatibility(context);
+            context.report(result)?;
+        }
+        Ok(())
+    }
+}
+
+impl DiscordApiCompatible for EditMessage {
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        if let Some(content) = self.content.as_ref() {
+            let result = interval_check(
+                &context.limits.content_len,
+                &content.len(),
+                "Content length");
+            context.report(result)?;
+        }
+        if let Some(action_rows) = self.action_rows.as_ref() {
+            let result = interval_check(
+                &context.limits.action_row_count,
+                &action_rows.len(),
+                "Action row count");
+            context.report(result)?;
+        }
+        if let Some(embeds) = self.embeds.as_ref() {
+            let result = interval_check(
+                &context.limits.embed_count,
+                &embeds.len(),
+                "Embed count");
+            context.report(result)?;
+        }
+
+        for embed in self.embeds.iter().flatten() {
+            let result = embed.check_compatibility(context);
+            context.report(result)?;
+        }
+
+        for row in self.action_rows.iter().flatten() {
+            let result = row.check_compatibility(context);
+            context.report(result)?;
+        }
+        Ok(())
     }
 }
 
 impl DiscordApiCompatible for Embed {
-    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), String> {
-        context.register_embed(self)?;
-        interval_check(&Self::FIELDS_LEN_INTERVAL, &self.fields.len(), "Embed field count")?;
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        let result = context.register_embed(self);
+        context.report(result)?;
+        let result = interval_check(&context.limits.embed_field_count, &self.fields.len(), "Embed field count");
+        context.report(result)?;
 
         if let Some(title) = self.title.as_ref() {
-            interval_check(&Self::TITLE_LEN_INTERVAL, &title.len(), "Embed title length")?;
+            let result = interval_check(&context.limits.embed_title_len, &title.len(), "Embed title length");
+            context.report(result)?;
         }
 
         if let Some(description) = self.description.as_ref() {
-            interval_check(&Self::DESCRIPTION_LEN_INTERVAL, &description.len(), "Embed description length")?;
+            let result = interval_check(&context.limits.embed_description_len, &description.len(), "Embed description length");
+            context.report(result)?;
         }
 
-        self.author.as_ref().map_or_else(|| Ok(()), |a| a.check_compatibility(context))?;
-        self.footer.as_ref().map_or_else(|| Ok(()), |f| f.check_compatibility(context))?;
+        let result = self.author.as_ref().map_or_else(|| Ok(()), |a| a.check_compatibility(context));
+        context.report(result)?;
+        let result = self.footer.as_ref().map_or_else(|| Ok(()), |f| f.check_compatibility(context));
+        context.report(result)?;
 
         for field in self.fields.iter() {
-            field.check_compatibility(context)?;
+            let result = field.check_compatibility(context);
+            context.report(result)?;
         }
         Ok(())
     }
 }
 
+impl DiscordApiCompatible for AllowedMentions {
+    fn check_compatibility(&self, _context: &mut MessageContext) -> Result<(), ValidationError> {
+        // whether `replied_user` is allowed depends on the message, see `Message::reply_to`
+        Ok(())
+    }
+}
+
+/// Checks that an icon URL uses one of the schemes Discord accepts for icons.
+fn check_icon_url(url: &str, field_name: &str) -> Result<(), ValidationError> {
+    const ICON_URL_SCHEMES: [&str; 3] = ["https://", "http://", "attachment://"];
+    if ICON_URL_SCHEMES
+        .iter()
+        .any(|scheme| url.starts_with(scheme) && url.len() > scheme.len())
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::Other(format!(
+            "{} ({}) is not a valid URL, only http(s) and attachment URLs are supported!",
+            field_name, url
+        )))
+    }
+}
+
 impl DiscordApiCompatible for EmbedAuthor {
-    fn check_compatibility(&self, _context: &mut MessageContext) -> Result<(), String> {
-        interval_check(&Self::NAME_LEN_INTERVAL, &self.name.len(), "Embed author name length")?;
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        interval_check(&context.limits.embed_author_name_len, &self.name.len(), "Embed author name length")?;
+        if let Some(icon_url) = self.icon_url.as_ref() {
+            check_icon_url(icon_url, "Embed author icon url")?;
+        }
         Ok(())
     }
 }
 
 impl DiscordApiCompatible for EmbedFooter {
-    fn check_compatibility(&self, _context: &mut MessageContext) -> Result<(), String> {
-        interval_check(&Self::TEXT_LEN_INTERVAL, &self.text.len(), "Embed footer text length")?;
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        interval_check(&context.limits.embed_footer_text_len, &self.text.len(), "Embed footer text length")?;
+        if let Some(icon_url) = self.icon_url.as_ref() {
+            check_icon_url(icon_url, "Embed footer icon url")?;
+        }
         Ok(())
     }
 }
 
 impl DiscordApiCompatible for EmbedField {
-    fn check_compatibility(&self, _context: &mut MessageContext) -> Result<(), String> {
-        interval_check(&Self::VALUE_LEN_INTERVAL, &self.value.len(), "Embed field value length")?;
-        interval_check(&Self::NAME_LEN_INTERVAL, &self.name.len(), "Embed field name length")?;
+    fn check_compatibility(&self, context: &mut MessageContext) -> Result<(), ValidationError> {
+        interval_check(&context.limits.embed_field_value_len, &self.value.len(), "Embed field value length")?;
+        interval_check(&context.limits.embed_field_name_len, &self.name.len(), "Embed field name length")?;
         Ok(())
     }
 }
\ No newline at end of file
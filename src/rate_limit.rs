@@ -0,0 +1,219 @@
+use hyper::HeaderMap;
+use serde::{Deserialize, Deserializer};
+
+use std::time::Duration;
+
+/// The shortest wait ever reported, guards against busy retry loops on `0` or tiny values.
+pub const MIN_RETRY_AFTER: Duration = Duration::from_millis(50);
+
+/// How failed sends are retried, see `WebhookClient::send_reliable`. Rate limited (429)
+/// sends are retried after the time Discord asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of retries after the first attempt
+    pub max_retries: u32,
+    /// Whether server errors (5xx) are retried
+    pub retry_server_errors: bool,
+    /// The wait before the first retry of a server error, doubled with every further retry.
+    /// Also used for rate limits when Discord does not say how long to wait.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            retry_server_errors: true,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before the retry number `retry` (starting at 0) of a server error.
+    pub fn server_error_delay(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// The relevant part of the body of a 429 (Too Many Requests) response.
+#[derive(Deserialize, Debug)]
+struct RateLimitBody {
+    #[serde(deserialize_with = "deserialize_seconds")]
+    retry_after: f64,
+}
+
+/// Reads a number of seconds given as an integer, a float or a string containing either.
+fn deserialize_seconds<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seconds {
+        Number(f64),
+        Text(String),
+    }
+
+    match Seconds::deserialize(deserializer)? {
+        Seconds::Number(seconds) => Ok(seconds),
+        Seconds::Text(text) => text.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Determines how long to wait before retrying a rate-limited request.
+///
+/// The `retry_after` field (seconds, as a float) of the JSON body is preferred, the
+/// `Retry-After` header (seconds) is used as a fallback. The result is rounded up to whole
+/// milliseconds and is never shorter than [`MIN_RETRY_AFTER`].
+///
+/// # Return value
+/// None if neither the body nor the headers contain a usable value.
+pub fn parse_retry_after(headers: &HeaderMap, body: &[u8]) -> Option<Duration> {
+    let from_body = serde_json::from_slice::<RateLimitBody>(body)
+        .ok()
+        .map(|body| body.retry_after);
+    let from_header = || {
+        headers
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<f64>().ok())
+    };
+
+    from_body
+        .or_else(from_header)
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(|seconds| Duration::from_millis((seconds * 1000.0).ceil() as u64).max(MIN_RETRY_AFTER))
+}
+
+/// Determines how long the rate limit bucket of a request stays exhausted, from the
+/// `X-RateLimit-Remaining` and `X-RateLimit-Reset-After` (seconds, as a float) headers
+/// of its response.
+///
+/// # Return value
+/// None if requests remain in the bucket or the headers are missing
+pub fn parse_bucket_reset_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<f64>().ok())
+    };
+    if header("x-ratelimit-remaining")? > 0.0 {
+        return None;
+    }
+    header("x-ratelimit-reset-after")
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(|seconds| Duration::from_millis((seconds * 1000.0).ceil() as u64))
+}
+
+/// Whether a 429 (Too Many Requests) response reports the global rate limit, which applies
+/// to all requests rather than to the route of the request.
+///
+/// The `X-RateLimit-Global` and `X-RateLimit-Scope` headers are checked, as well as the
+/// `global` field of the JSON body.
+pub fn is_global_rate_limit(headers: &HeaderMap, body: &[u8]) -> bool {
+    let header_is = |name: &str, expected: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case(expected))
+    };
+    header_is("x-ratelimit-global", "true")
+        || header_is("x-ratelimit-scope", "global")
+        || serde_json::from_slice::<serde_json::Value>(body)
+            .ok()
+            .and_then(|body| body.get("global").and_then(serde_json::Value::as_bool))
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_global_rate_limit, parse_bucket_reset_after, parse_retry_after, RetryPolicy, MIN_RETRY_AFTER};
+    use hyper::HeaderMap;
+    use std::time::Duration;
+
+    fn headers_with_retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_from_header_only() {
+        let headers = headers_with_retry_after("3");
+        assert_eq!(parse_retry_after(&headers, b""), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn retry_after_from_body_only() {
+        let body = br#"{"message": "You are being rate limited.", "retry_after": 1.2345, "global": false}"#;
+        assert_eq!(
+            parse_retry_after(&HeaderMap::new(), body),
+            Some(Duration::from_millis(1235))
+        );
+    }
+
+    #[test]
+    fn retry_after_body_representations() {
+        let parse = |body: &[u8]| parse_retry_after(&HeaderMap::new(), body);
+        assert_eq!(parse(br#"{"retry_after": 5}"#), Some(Duration::from_secs(5)));
+        assert_eq!(parse(br#"{"retry_after": 5.2}"#), Some(Duration::from_millis(5200)));
+        assert_eq!(parse(br#"{"retry_after": "5.2"}"#), Some(Duration::from_millis(5200)));
+        assert_eq!(parse(br#"{"retry_after": "soon"}"#), None);
+    }
+
+    #[test]
+    fn retry_after_prefers_body() {
+        let headers = headers_with_retry_after("3");
+        let body = br#"{"retry_after": 0.5}"#;
+        assert_eq!(parse_retry_after(&headers, body), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn retry_after_has_minimum() {
+        let body = br#"{"retry_after": 0}"#;
+        assert_eq!(parse_retry_after(&HeaderMap::new(), body), Some(MIN_RETRY_AFTER));
+    }
+
+    #[test]
+    fn server_error_delay_doubles() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.server_error_delay(0), Duration::from_millis(100));
+        assert_eq!(policy.server_error_delay(3), Duration::from_millis(800));
+    }
+
+    #[test]
+    fn global_rate_limit_detected() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_global_rate_limit(&headers, br#"{"retry_after": 1, "global": false}"#));
+        assert!(is_global_rate_limit(&headers, br#"{"retry_after": 1, "global": true}"#));
+        headers.insert("x-ratelimit-scope", "global".parse().unwrap());
+        assert!(is_global_rate_limit(&headers, b""));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-scope", "user".parse().unwrap());
+        assert!(!is_global_rate_limit(&headers, b""));
+        headers.insert("x-ratelimit-global", "true".parse().unwrap());
+        assert!(is_global_rate_limit(&headers, b""));
+    }
+
+    #[test]
+    fn retry_after_missing() {
+        assert_eq!(parse_retry_after(&HeaderMap::new(), b"not json"), None);
+    }
+
+    #[test]
+    fn bucket_reset_after_exhausted_bucket() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", "0".parse().unwrap());
+        headers.insert("x-ratelimit-reset-after", "1.25".parse().unwrap());
+        assert_eq!(parse_bucket_reset_after(&headers), Some(Duration::from_millis(1250)));
+
+        headers.insert("x-ratelimit-remaining", "3".parse().unwrap());
+        assert_eq!(parse_bucket_reset_after(&headers), None);
+        assert_eq!(parse_bucket_reset_after(&HeaderMap::new()), None);
+    }
+}
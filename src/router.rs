@@ -0,0 +1,83 @@
+//! Routing of component interactions to handlers by the prefix of their custom id.
+
+use std::collections::HashMap;
+
+use crate::models::{CustomId, ValidationError};
+
+type Handler<R> = Box<dyn Fn(&CustomId) -> R + Send + Sync>;
+
+/// Maps custom id prefixes (see [`CustomId`]) to handlers, so that the interactions of the
+/// components sent with such custom ids can be dispatched to the right handler.
+///
+/// The router only routes, receiving the interactions (and responding to them) is left to
+/// the caller.
+///
+/// # Example
+/// ```
+/// # use webhook::models::CustomId;
+/// # use webhook::router::InteractionRouter;
+/// let mut router = InteractionRouter::new();
+/// router.route("vote", |id: &CustomId| format!("voted {}", id.args()[0]));
+///
+/// let custom_id = CustomId::new("vote").arg("yes").build().unwrap();
+/// assert_eq!(router.dispatch(&custom_id).unwrap(), Some("voted yes".to_string()));
+/// ```
+pub struct InteractionRouter<R> {
+    handlers: HashMap<String, Handler<R>>,
+}
+
+impl<R> Default for InteractionRouter<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> InteractionRouter<R> {
+    pub fn new() -> Self {
+        InteractionRouter {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Registers the handler of the custom ids with the `prefix`, replacing the handler
+    /// registered for the prefix before.
+    pub fn route<F>(&mut self, prefix: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&CustomId) -> R + Send + Sync + 'static,
+    {
+        self.handlers.insert(prefix.to_string(), Box::new(handler));
+        self
+    }
+
+    /// Calls the handler registered for the prefix of `custom_id` with the parsed custom id.
+    ///
+    /// # Return value
+    /// The result of the handler, None if no handler is registered for the prefix.
+    /// Error variant contains an error message (the custom id is not valid)
+    pub fn dispatch(&self, custom_id: &str) -> Result<Option<R>, ValidationError> {
+        let custom_id = CustomId::parse(custom_id)?;
+        Ok(self
+            .handlers
+            .get(custom_id.prefix())
+            .map(|handler| handler(&custom_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InteractionRouter;
+    use crate::models::CustomId;
+
+    #[test]
+    fn dispatched_by_prefix() {
+        let mut router = InteractionRouter::new();
+        router
+            .route("vote", |id: &CustomId| format!("vote {}", id.args().join(",")))
+            .route("page", |id: &CustomId| format!("page {}", id.args()[0]));
+
+        assert_eq!(router.dispatch("vote:yes:42").unwrap().as_deref(), Some("vote yes,42"));
+        assert_eq!(router.dispatch("page:3").unwrap().as_deref(), Some("page 3"));
+        assert_eq!(router.dispatch("unknown:1").unwrap(), None);
+        assert!(router.dispatch("").is_err());
+    }
+}